@@ -0,0 +1,184 @@
+//! `command-runner-tui run <name>`: run a single configured command without
+//! the TUI and exit with its exit code.
+
+use crate::{
+    config::{self, CommandEntry, Config},
+    expand,
+    logging::log,
+    runner,
+};
+
+use std::{
+    fmt::Write,
+    io,
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runs the command called `name`, streaming its output or, with `json`,
+/// printing it as one object once it's done. Returns the exit code to exit
+/// with.
+pub fn run(config: &Config, name: &str, json: bool) -> i32 {
+    let Some(entry) = config.find(name) else {
+        eprintln!("no command named `{}` in the config", name);
+        return 2;
+    };
+    if !entry.supports_current_os() {
+        eprintln!(
+            "`{}` is only available on {}",
+            entry.command,
+            entry.os.join(", ")
+        );
+        return 2;
+    }
+
+    // its `depends_on` first, then the command itself
+    let order: Vec<CommandEntry> = match config::run_order(&config.commands, entry) {
+        Ok(order) => order.into_iter().cloned().collect(),
+        Err(err) => {
+            eprintln!("{}", err);
+            return 2;
+        }
+    };
+
+    log!("batch run of {:?}", entry.command);
+    if !json {
+        // stdio is inherited, so output streams straight to the real terminal
+        return run_entries(&order, |step, stdin| {
+            runner::spawn_with_stdin(&mut build(config, step), stdin)?.wait()
+        });
+    }
+
+    let started = Instant::now();
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let exit_code = run_entries(&order, |step, stdin| {
+        // like `output()`, which reads nothing from our stdin
+        let mut process = build(config, step);
+        process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let output = runner::spawn_with_stdin(&mut process, stdin)?.wait_with_output()?;
+        stdout.extend_from_slice(&output.stdout);
+        stderr.extend_from_slice(&output.stderr);
+        Ok(output.status)
+    });
+    println!(
+        "{}",
+        json_report(
+            &entry.command,
+            exit_code,
+            &String::from_utf8_lossy(&stdout),
+            &String::from_utf8_lossy(&stderr),
+            started.elapsed(),
+        )
+    );
+    exit_code
+}
+
+// runs the entries one after another, stopping at the first that fails,
+// and returns the exit code of the run
+fn run_entries(
+    entries: &[CommandEntry],
+    mut spawn: impl FnMut(&str, Option<&str>) -> io::Result<ExitStatus>,
+) -> i32 {
+    let chained = entries.len() > 1;
+    for (i, entry) in entries.iter().enumerate() {
+        if chained {
+            eprintln!("── {}/{}: {} ──", i + 1, entries.len(), entry.command);
+        }
+        let exit_code = run_steps(entry, &mut spawn);
+        if exit_code != 0 {
+            return exit_code;
+        }
+    }
+    log!("batch run finished");
+    0
+}
+
+// the process for a step, with its variables expanded only now so their
+// values stay out of what gets printed and logged about it
+fn build(config: &Config, step: &str) -> Command {
+    runner::build(&expand::expand(step, config.undefined_env))
+}
+
+// runs the steps of `entry` one after another with `spawn`, which gets the
+// entry's `stdin` too, retrying failed ones, and returns the exit code of
+// the entry
+fn run_steps(
+    entry: &CommandEntry,
+    spawn: &mut impl FnMut(&str, Option<&str>) -> io::Result<ExitStatus>,
+) -> i32 {
+    for step in entry.spawned_commands() {
+        let mut attempt = 0;
+        loop {
+            match spawn(&step, entry.stdin.as_deref()) {
+                Ok(status) if status.success() => {
+                    log!("batch step {:?} finished", step);
+                    break;
+                }
+                Ok(status) if attempt < entry.retries => {
+                    attempt += 1;
+                    eprintln!(
+                        "`{}` failed, retry {}/{} in {}s",
+                        step,
+                        attempt,
+                        entry.retries,
+                        runner::RETRY_DELAY.as_secs()
+                    );
+                    log!("batch step {:?} failed, {}, retrying", step, status);
+                    thread::sleep(runner::RETRY_DELAY);
+                }
+                Ok(status) => {
+                    log!("batch step {:?} failed, {}", step, status);
+                    return runner::exit_code(status);
+                }
+                Err(err) => {
+                    eprintln!("failed to run `{}`: {}", step, err);
+                    return 127;
+                }
+            }
+        }
+    }
+    0
+}
+
+// `{"command": ..., "exit_code": ..., "stdout": ..., "stderr": ..., "duration_ms": ...}`
+fn json_report(
+    command: &str,
+    exit_code: i32,
+    stdout: &str,
+    stderr: &str,
+    duration: Duration,
+) -> String {
+    format!(
+        "{{\"command\": {}, \"exit_code\": {}, \"stdout\": {}, \"stderr\": {}, \"duration_ms\": {}}}",
+        json_string(command),
+        exit_code,
+        json_string(stdout),
+        json_string(stderr),
+        duration.as_millis()
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
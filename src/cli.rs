@@ -0,0 +1,73 @@
+//! Command line flags.
+
+use std::{env, path::PathBuf};
+
+pub const USAGE: &str =
+    "usage: command-runner-tui [--config <path>]... [--stdin] [--log <path>] [run <name> [--json] | export [<path>]]";
+
+#[derive(Debug, PartialEq)]
+pub enum Subcommand {
+    /// run one configured command without the TUI
+    Run {
+        name: String,
+        /// print one JSON object with the captured output instead of
+        /// streaming it
+        json: bool,
+    },
+    /// write the command list as a script, to stdout without a path
+    Export { path: Option<PathBuf> },
+}
+
+#[derive(Debug, Default)]
+pub struct Cli {
+    /// overrides the default config location; several are merged in order,
+    /// and a directory stands for the `.toml` files in it
+    pub config: Vec<PathBuf>,
+    /// read the command list from stdin, one command per line
+    pub stdin: bool,
+    /// append a debug log of the app's own activity to this file
+    pub log: Option<PathBuf>,
+    pub subcommand: Option<Subcommand>,
+}
+
+impl Cli {
+    pub fn from_env() -> Result<Cli, String> {
+        Cli::parse(env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = args.next().ok_or("--config needs a path")?;
+                    cli.config.push(PathBuf::from(path));
+                }
+                "--stdin" => cli.stdin = true,
+                "--log" => {
+                    let path = args.next().ok_or("--log needs a path")?;
+                    cli.log = Some(PathBuf::from(path));
+                }
+                "run" if cli.subcommand.is_none() => {
+                    let name = args.next().ok_or("run needs a command name")?;
+                    cli.subcommand = Some(Subcommand::Run { name, json: false });
+                }
+                "export" if cli.subcommand.is_none() => {
+                    let path = args.next_if(|arg| !arg.starts_with("--"));
+                    cli.subcommand = Some(Subcommand::Export {
+                        path: path.map(PathBuf::from),
+                    });
+                }
+                "--json" => match &mut cli.subcommand {
+                    Some(Subcommand::Run { json, .. }) => *json = true,
+                    _ => return Err("--json only goes with run".to_string()),
+                },
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+
+        Ok(cli)
+    }
+}
@@ -0,0 +1,75 @@
+//! Copying text to the system clipboard, with the `clipboard` feature.
+//!
+//! The text is piped into whichever clipboard tool the platform has
+//! (`pbcopy`, `clip`, `wl-copy`, `xclip` or `xsel`).
+
+use std::io;
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> io::Result<()> {
+    use std::{
+        env,
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])]
+    } else {
+        &[
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+
+    for (program, args) in tools {
+        let mut child = match Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        // dropping stdin closes it, which is what makes the tool finish; it's
+        // waited for even when the write failed so it isn't left a zombie
+        let written = child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes());
+        let status = child.wait()?;
+        written?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                program, status
+            )));
+        }
+        return Ok(());
+    }
+
+    let names: Vec<&str> = tools.iter().map(|(program, _)| *program).collect();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "no clipboard tool found, install one of {}",
+            names.join(", ")
+        ),
+    ))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "clipboard support is not compiled in",
+    ))
+}
@@ -0,0 +1,1484 @@
+//! User configuration, read from a `commands.toml` file.
+//!
+//! This understands just the subset of TOML the config needs: comments,
+//! `key = value` pairs, `[table]` and `[[array-of-tables]]` headers, and
+//! string / integer / boolean / array values.
+
+use crate::{
+    expand, filters, logging::log, notify::Notify, progress, spinner::SpinnerStyle, template, theme,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    // serializes back to TOML
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\r' => write!(f, "\\r")?,
+                        c if c.is_control() => write!(f, "\\u{:04X}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+/// A parsed file before it is interpreted as a [`Config`].
+#[derive(Debug, Default, Clone)]
+pub struct Document {
+    pub root: Table,
+    pub tables: BTreeMap<String, Table>,
+    pub arrays: BTreeMap<String, Vec<Table>>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    Invalid(String),
+    /// an error in one of several merged files
+    File {
+        path: PathBuf,
+        error: Box<ConfigError>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not read config: {}", err),
+            ConfigError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+            ConfigError::File { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+/// One entry of the command list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandEntry {
+    pub command: String,
+    /// what `run <name>` looks the command up by, defaults to the command
+    pub name: Option<String>,
+    /// short label or emoji rendered in front of the command
+    pub icon: Option<String>,
+    pub description: Option<String>,
+    /// extra names the search also matches on
+    pub aliases: Vec<String>,
+    /// labels like `network` or `danger`, searched with `tag:<name>`; a
+    /// leading `#` in the config is dropped
+    pub tags: Vec<String>,
+    /// run under a pseudo-terminal instead of pipes (unix only, needs the `pty`
+    /// feature)
+    pub pty: bool,
+    /// operating systems the command runs on (`linux`, `macos`, `windows`,
+    /// or the `unix` family), empty for all of them
+    pub os: Vec<String>,
+    /// commands run one after another instead of `command`, stopping at the
+    /// first one that fails
+    pub steps: Vec<String>,
+    /// names of the [`filters`] the output is passed through
+    pub filters: Vec<String>,
+    /// ask before running, for destructive commands
+    pub confirm: bool,
+    /// how risky the command is: `medium` and `high` ask before running
+    /// like `confirm`, and the list colors them
+    pub danger: Danger,
+    /// shown in the question before a confirmed command runs
+    pub confirm_message: Option<String>,
+    /// written to the command's stdin, which is then closed; given to each
+    /// of its `steps`
+    pub stdin: Option<String>,
+    /// how often a failed command (or failed step) is run again before the
+    /// run counts as failed, for flaky network checks and the like
+    pub retries: u32,
+    /// run on this machine over `ssh` instead of locally
+    pub host: Option<String>,
+    /// extra arguments for `ssh`, before the host
+    pub ssh_options: Vec<String>,
+    /// where the output says how far along the run is, see [`progress`]
+    pub progress: Option<String>,
+    /// overrides the global `notify_on_complete`
+    pub notify_on_complete: Option<Notify>,
+    /// commands (looked up like `run <name>`) that have to succeed before
+    /// this one runs, see [`run_order`]
+    pub depends_on: Vec<String>,
+}
+
+impl CommandEntry {
+    pub fn new(command: &str) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            name: None,
+            icon: None,
+            description: None,
+            aliases: Vec::new(),
+            tags: Vec::new(),
+            pty: false,
+            os: Vec::new(),
+            steps: Vec::new(),
+            filters: Vec::new(),
+            confirm: false,
+            danger: Danger::default(),
+            confirm_message: None,
+            stdin: None,
+            retries: 0,
+            host: None,
+            ssh_options: Vec::new(),
+            progress: None,
+            notify_on_complete: None,
+            depends_on: Vec::new(),
+        }
+    }
+
+    /// What actually gets run: the steps of a chain, or just the command.
+    pub fn step_commands(&self) -> Vec<String> {
+        if self.steps.is_empty() {
+            vec![self.command.clone()]
+        } else {
+            self.steps.clone()
+        }
+    }
+
+    /// What gets spawned for each step: the [`step_commands`](Self::step_commands),
+    /// run through `ssh` when the command has a `host`.
+    pub fn spawned_commands(&self) -> Vec<String> {
+        let steps = self.step_commands();
+        let Some(host) = &self.host else {
+            return steps;
+        };
+        let mut ssh = vec!["ssh"];
+        // ssh only allocates a remote terminal when asked to
+        if self.pty {
+            ssh.push("-t");
+        }
+        ssh.extend(self.ssh_options.iter().map(String::as_str));
+        ssh.push(host);
+        let ssh = ssh.join(" ");
+        steps
+            .into_iter()
+            .map(|step| format!("{} {}", ssh, step))
+            .collect()
+    }
+
+    /// The `{placeholder}` names used by the command or its steps, see
+    /// [`template`].
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names = template::placeholders(&self.command);
+        for step in &self.steps {
+            for name in template::placeholders(step) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// A copy with every placeholder replaced by its value.
+    pub fn fill(&self, values: &HashMap<String, String>) -> CommandEntry {
+        CommandEntry {
+            command: template::fill(&self.command, values),
+            steps: self
+                .steps
+                .iter()
+                .map(|step| template::fill(step, values))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the command asks before it runs, through `confirm` or its
+    /// `danger`.
+    pub fn needs_confirm(&self) -> bool {
+        self.confirm || self.danger != Danger::Low
+    }
+
+    /// Whether the command can run on the OS this binary was built for.
+    pub fn supports_current_os(&self) -> bool {
+        self.os.is_empty()
+            || self.os.iter().any(|os| {
+                os.eq_ignore_ascii_case(env::consts::OS)
+                    || os.eq_ignore_ascii_case(env::consts::FAMILY)
+            })
+    }
+
+    /// Whether one of the tags is `tag`, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+
+    /// Every field the search box is matched against.
+    pub fn searchable_fields(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.command.as_str())
+            .chain(self.name.as_deref())
+            .chain(self.description.as_deref())
+            .chain(self.aliases.iter().map(String::as_str))
+    }
+
+    fn from_table(table: &Table, index: usize) -> Result<CommandEntry, ConfigError> {
+        let context = format!("commands[{}]", index);
+        let steps = get_string_array(table, "steps", &context)?.unwrap_or_default();
+        let filters = get_string_array(table, "filters", &context)?.unwrap_or_default();
+        if let Some(unknown) = filters.iter().find(|name| filters::by_name(name).is_none()) {
+            return Err(ConfigError::Invalid(format!(
+                "{}: unknown filter `{}`, expected one of {}",
+                context,
+                unknown,
+                filters::NAMES.join(", ")
+            )));
+        }
+        let retries = match get_integer(table, "retries", &context)? {
+            None => 0,
+            Some(n) => u32::try_from(n).map_err(|_| {
+                ConfigError::Invalid(format!("{}: `retries` can't be negative", context))
+            })?,
+        };
+        let danger = match get_string(table, "danger", &context)? {
+            Some(name) => Danger::from_name(&name).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "{}: unknown danger `{}`, expected one of {}",
+                    context,
+                    name,
+                    Danger::NAMES.join(", ")
+                ))
+            })?,
+            None => Danger::default(),
+        };
+        let progress = get_string(table, "progress", &context)?;
+        if progress
+            .as_deref()
+            .is_some_and(|pattern| !progress::is_valid(pattern))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "{}: `progress` needs exactly one `{{}}` where the percentage is",
+                context
+            )));
+        }
+        // a chain without its own `command` is shown like the shell would run it
+        let command = get_string(table, "command", &context)?
+            .or_else(|| (!steps.is_empty()).then(|| steps.join(" && ")))
+            .ok_or_else(|| {
+                ConfigError::Invalid(format!("{}: missing `command` or `steps`", context))
+            })?;
+
+        Ok(CommandEntry {
+            command,
+            name: get_string(table, "name", &context)?,
+            icon: get_string(table, "icon", &context)?.filter(|icon| !icon.is_empty()),
+            description: get_string(table, "description", &context)?,
+            aliases: get_string_array(table, "aliases", &context)?.unwrap_or_default(),
+            tags: get_string_array(table, "tags", &context)?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| tag.trim_start_matches('#').to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            pty: get_bool(table, "pty", &context)?.unwrap_or(false),
+            // a single OS can be given as a plain string
+            os: match table.get("os") {
+                Some(Value::String(os)) => vec![os.clone()],
+                _ => get_string_array(table, "os", &context)?.unwrap_or_default(),
+            },
+            steps,
+            filters,
+            confirm: get_bool(table, "confirm", &context)?.unwrap_or(false),
+            danger,
+            confirm_message: get_string(table, "confirm_message", &context)?,
+            stdin: get_string(table, "stdin", &context)?,
+            retries,
+            host: get_string(table, "host", &context)?.filter(|host| !host.is_empty()),
+            ssh_options: get_string_array(table, "ssh_options", &context)?.unwrap_or_default(),
+            progress,
+            notify_on_complete: get_notify(table, &context)?,
+            depends_on: get_string_array(table, "depends_on", &context)?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Looks a command up by name, then by its exact command string, then by
+/// alias.
+pub fn find_command<'a>(commands: &'a [CommandEntry], name: &str) -> Option<&'a CommandEntry> {
+    commands
+        .iter()
+        .find(|entry| entry.name.as_deref() == Some(name))
+        .or_else(|| commands.iter().find(|entry| entry.command == name))
+        .or_else(|| {
+            commands
+                .iter()
+                .find(|entry| entry.aliases.iter().any(|alias| alias == name))
+        })
+}
+
+/// What running `entry` runs: its `depends_on`, theirs before them, each
+/// once, and then `entry` itself. Fails on a name that isn't in `commands`
+/// and on a cycle.
+pub fn run_order<'a>(
+    commands: &'a [CommandEntry],
+    entry: &'a CommandEntry,
+) -> Result<Vec<&'a CommandEntry>, ConfigError> {
+    let mut order = Vec::new();
+    visit(commands, entry, &mut Vec::new(), &mut order)?;
+    Ok(order)
+}
+
+// depth first, `path` is the chain of dependencies that led to `entry`
+fn visit<'a>(
+    commands: &'a [CommandEntry],
+    entry: &'a CommandEntry,
+    path: &mut Vec<&'a str>,
+    order: &mut Vec<&'a CommandEntry>,
+) -> Result<(), ConfigError> {
+    let key = |entry: &'a CommandEntry| entry.name.as_deref().unwrap_or(&entry.command);
+    let name = key(entry);
+    if let Some(start) = path.iter().position(|seen| *seen == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name);
+        return Err(ConfigError::Invalid(format!(
+            "dependency cycle: {}",
+            cycle.join(" -> ")
+        )));
+    }
+    if order.iter().any(|done| key(done) == name) {
+        return Ok(());
+    }
+
+    path.push(name);
+    for dependency in &entry.depends_on {
+        let found = find_command(commands, dependency).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "`{}` depends on `{}`, which isn't a command",
+                name, dependency
+            ))
+        })?;
+        visit(commands, found, path, order)?;
+    }
+    path.pop();
+    order.push(entry);
+    Ok(())
+}
+
+/// How the command list and the output share the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PaneLayout {
+    /// list above the output
+    #[default]
+    Vertical,
+    /// list left of the output
+    Horizontal,
+}
+
+impl PaneLayout {
+    pub const NAMES: &'static [&'static str] = &["vertical", "horizontal"];
+
+    pub fn from_name(name: &str) -> Option<PaneLayout> {
+        match name {
+            "vertical" => Some(PaneLayout::Vertical),
+            "horizontal" => Some(PaneLayout::Horizontal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaneLayout::Vertical => "vertical",
+            PaneLayout::Horizontal => "horizontal",
+        }
+    }
+
+    pub fn toggled(self) -> PaneLayout {
+        match self {
+            PaneLayout::Vertical => PaneLayout::Horizontal,
+            PaneLayout::Horizontal => PaneLayout::Vertical,
+        }
+    }
+}
+
+/// `danger` of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Danger {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl Danger {
+    pub const NAMES: &'static [&'static str] = &["low", "medium", "high"];
+
+    pub fn from_name(name: &str) -> Option<Danger> {
+        match name {
+            "low" => Some(Danger::Low),
+            "medium" => Some(Danger::Medium),
+            "high" => Some(Danger::High),
+            _ => None,
+        }
+    }
+}
+
+/// How long the event loop waits for input before redrawing.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How often the spinner advances a frame.
+pub const DEFAULT_SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+// lower than this just burns CPU without looking any different
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const MIN_SPINNER_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub commands: Vec<CommandEntry>,
+    /// commands (by command string) pinned to the top of the list
+    pub favorites: Vec<String>,
+    pub spinner: SpinnerStyle,
+    pub layout: PaneLayout,
+    pub theme: theme::Preset,
+    /// prefix output lines with the time since the run started
+    pub timestamps: bool,
+    /// `search_runs_first`: Enter in the search box runs the top result
+    /// right away instead of going back to the list
+    pub search_runs_first: bool,
+    /// `undefined_env`: what unset environment variables in commands
+    /// expand to
+    pub undefined_env: expand::Undefined,
+    /// how finished runs are announced, unless the command says otherwise
+    pub notify_on_complete: Notify,
+    /// `poll_interval_ms`
+    pub poll_interval: Duration,
+    /// `spinner_interval_ms`
+    pub spinner_interval: Duration,
+    /// where settings changed from the UI are written back to
+    pub path: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Choose appropriate commands based on OS
+        let commands = if env::consts::OS == "windows" {
+            [
+                "dir",
+                "echo \"testing\"",
+                "ipconfig",
+                "systeminfo",
+                "whoami",
+                "tasklist",
+            ]
+        } else {
+            [
+                "ls",
+                "echo \"testing\"",
+                "ifconfig",
+                "uname -a",
+                "whoami",
+                "ps aux",
+            ]
+        };
+
+        Config {
+            commands: commands.iter().map(|cmd| CommandEntry::new(cmd)).collect(),
+            favorites: Vec::new(),
+            spinner: SpinnerStyle::default(),
+            layout: PaneLayout::default(),
+            theme: theme::Preset::default(),
+            timestamps: false,
+            search_runs_first: false,
+            undefined_env: expand::Undefined::default(),
+            notify_on_complete: Notify::default(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            spinner_interval: DEFAULT_SPINNER_INTERVAL,
+            path: None,
+        }
+    }
+}
+
+impl Config {
+    /// Looks a command up by name, see [`find_command`].
+    pub fn find(&self, name: &str) -> Option<&CommandEntry> {
+        find_command(&self.commands, name)
+    }
+
+    /// Reads the config at `path`, or the default location when `None`.
+    /// A missing default file is not an error, it just means the built-in
+    /// commands are used.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                path => {
+                    return Ok(Config {
+                        path,
+                        ..Config::default()
+                    })
+                }
+            },
+        };
+
+        let mut config = Config::parse(&fs::read_to_string(&path)?)?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    /// Reads several configs and merges them in order, see
+    /// [`Document::merge`]: settings and commands (by name) of later files
+    /// win. A directory stands for the `.toml` files in it, by file name.
+    /// Settings changed from the UI are written to the last file.
+    pub fn load_all(paths: &[PathBuf]) -> Result<Config, ConfigError> {
+        let files = config_files(paths)?;
+        if files.len() <= 1 {
+            return Config::load(files.first().map(PathBuf::as_path));
+        }
+
+        let mut merged = Document::default();
+        for path in &files {
+            let in_file = |error| ConfigError::File {
+                path: path.clone(),
+                error: Box::new(error),
+            };
+            let source = fs::read_to_string(path).map_err(|err| in_file(err.into()))?;
+            let document = parse_document(&source).map_err(in_file)?;
+            // checked on its own, so errors point at the right file and
+            // entry; `depends_on` can name commands of the other files, so
+            // the dependencies are only checked once they're merged
+            Config::read_document(document.clone()).map_err(in_file)?;
+            merged.merge(document, path);
+        }
+        log!("merged {} config files", files.len());
+
+        let mut config = Config::from_document(merged)?;
+        config.path = files.last().cloned();
+        Ok(config)
+    }
+
+    pub fn parse(source: &str) -> Result<Config, ConfigError> {
+        Config::from_document(parse_document(source)?)
+    }
+
+    pub fn from_document(document: Document) -> Result<Config, ConfigError> {
+        let config = Config::read_document(document)?;
+        for entry in &config.commands {
+            run_order(&config.commands, entry)?;
+        }
+        Ok(config)
+    }
+
+    // everything but the `depends_on` check
+    fn read_document(document: Document) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+
+        if let Some(entries) = document.arrays.get("commands") {
+            if !entries.is_empty() {
+                config.commands = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, table)| CommandEntry::from_table(table, i))
+                    .collect::<Result<_, _>>()?;
+            }
+        }
+        config.favorites =
+            get_string_array(&document.root, "favorites", "config")?.unwrap_or_default();
+        if let Some(name) = get_string(&document.root, "spinner", "config")? {
+            config.spinner = SpinnerStyle::from_name(&name).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "config: unknown spinner `{}`, expected one of {}",
+                    name,
+                    SpinnerStyle::NAMES.join(", ")
+                ))
+            })?;
+        }
+        if let Some(name) = get_string(&document.root, "layout", "config")? {
+            config.layout = PaneLayout::from_name(&name).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "config: unknown layout `{}`, expected one of {}",
+                    name,
+                    PaneLayout::NAMES.join(", ")
+                ))
+            })?;
+        }
+        if let Some(name) = get_string(&document.root, "theme", "config")? {
+            config.theme = theme::Preset::from_name(&name).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "config: unknown theme `{}`, expected one of {}",
+                    name,
+                    theme::Preset::NAMES.join(", ")
+                ))
+            })?;
+        }
+        if let Some(timestamps) = get_bool(&document.root, "timestamps", "config")? {
+            config.timestamps = timestamps;
+        }
+        if let Some(runs_first) = get_bool(&document.root, "search_runs_first", "config")? {
+            config.search_runs_first = runs_first;
+        }
+        if let Some(name) = get_string(&document.root, "undefined_env", "config")? {
+            config.undefined_env = expand::Undefined::from_name(&name).ok_or_else(|| {
+                ConfigError::Invalid(format!(
+                    "config: unknown undefined_env `{}`, expected one of {}",
+                    name,
+                    expand::Undefined::NAMES.join(", ")
+                ))
+            })?;
+        }
+        if let Some(notify) = get_notify(&document.root, "config")? {
+            config.notify_on_complete = notify;
+        }
+        if let Some(interval) = get_interval(&document.root, "poll_interval_ms", MIN_POLL_INTERVAL)?
+        {
+            config.poll_interval = interval;
+        }
+        if let Some(interval) =
+            get_interval(&document.root, "spinner_interval_ms", MIN_SPINNER_INTERVAL)?
+        {
+            config.spinner_interval = interval;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Writes a single top-level `key = value` setting into the file at `path`,
+/// replacing a previous value for the key and leaving the rest of the file
+/// (comments, commands, formatting) untouched. The file is created when it
+/// doesn't exist yet.
+pub fn write_setting(path: &Path, key: &str, value: &Value) -> io::Result<()> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    let statement = format!("{} = {}\n", key, value);
+
+    let updated = match find_setting(&source, key) {
+        Some((start, end)) => format!("{}{}{}", &source[..start], statement, &source[end..]),
+        None => format!("{}{}", statement, source),
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, updated)
+}
+
+// byte range of the top-level `key = ...` statement including its newline
+fn find_setting(source: &str, key: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            // settings live above the first table header
+            return None;
+        }
+        let is_key = trimmed
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if is_key {
+            return Some((offset, offset + statement_len(&source[offset..])));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+// length of a statement that may continue over several lines while an
+// array is open
+fn statement_len(statement: &str) -> usize {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut in_comment = false;
+    let mut chars = statement.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' {
+            if depth == 0 {
+                return i + 1;
+            }
+            quote = None;
+            in_comment = false;
+            continue;
+        }
+        if in_comment {
+            continue;
+        }
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => in_comment = true,
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    statement.len()
+}
+
+/// `$XDG_CONFIG_HOME/command-runner/commands.toml` (or the `%APPDATA%`
+/// equivalent on Windows).
+pub fn default_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    dir.map(|dir| dir.join("command-runner").join("commands.toml"))
+}
+
+fn get_string(table: &Table, key: &str, context: &str) -> Result<Option<String>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(other) => Err(type_error(context, key, "a string", other)),
+    }
+}
+
+fn get_integer(table: &Table, key: &str, context: &str) -> Result<Option<i64>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Integer(n)) => Ok(Some(*n)),
+        Some(other) => Err(type_error(context, key, "an integer", other)),
+    }
+}
+
+// a `<key> = <milliseconds>` setting that can't go below `min`
+fn get_interval(table: &Table, key: &str, min: Duration) -> Result<Option<Duration>, ConfigError> {
+    let Some(millis) = get_integer(table, key, "config")? else {
+        return Ok(None);
+    };
+    match u64::try_from(millis).map(Duration::from_millis) {
+        Ok(interval) if interval >= min => Ok(Some(interval)),
+        _ => Err(ConfigError::Invalid(format!(
+            "config: `{}` must be at least {}",
+            key,
+            min.as_millis()
+        ))),
+    }
+}
+
+// `notify_on_complete`, one of the `Notify` names or a boolean for the bell
+fn get_notify(table: &Table, context: &str) -> Result<Option<Notify>, ConfigError> {
+    let key = "notify_on_complete";
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Boolean(true)) => Ok(Some(Notify::Bell)),
+        Some(Value::Boolean(false)) => Ok(Some(Notify::Off)),
+        Some(Value::String(name)) => Notify::from_name(name).map(Some).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "{}: unknown {} `{}`, expected a boolean or one of {}",
+                context,
+                key,
+                name,
+                Notify::NAMES.join(", ")
+            ))
+        }),
+        Some(other) => Err(type_error(context, key, "a string or a boolean", other)),
+    }
+}
+
+fn get_bool(table: &Table, key: &str, context: &str) -> Result<Option<bool>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Boolean(b)) => Ok(Some(*b)),
+        Some(other) => Err(type_error(context, key, "a boolean", other)),
+    }
+}
+
+fn get_string_array(
+    table: &Table,
+    key: &str,
+    context: &str,
+) -> Result<Option<Vec<String>>, ConfigError> {
+    let items = match table.get(key) {
+        None => return Ok(None),
+        Some(Value::Array(items)) => items,
+        Some(other) => return Err(type_error(context, key, "an array of strings", other)),
+    };
+
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(s) => Ok(s.clone()),
+            other => Err(type_error(context, key, "an array of strings", other)),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn type_error(context: &str, key: &str, expected: &str, found: &Value) -> ConfigError {
+    ConfigError::Invalid(format!(
+        "{}: `{}` must be {}, found {}",
+        context,
+        key,
+        expected,
+        found.type_name()
+    ))
+}
+
+// the files `paths` stand for, directories expanded
+fn config_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, ConfigError> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut found: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.is_file() && file.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        if found.is_empty() {
+            return Err(ConfigError::Invalid(format!(
+                "{}: no .toml files in the directory",
+                path.display()
+            )));
+        }
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+impl Document {
+    /// Lays `other` (read from `path`) over this one: its settings replace
+    /// these, and its `[[commands]]` replace the ones with the same name
+    /// (or command, for entries without one) in place, new ones go last.
+    /// Every override is logged.
+    pub fn merge(&mut self, other: Document, path: &Path) {
+        for (key, value) in other.root {
+            if self.root.get(&key).is_some_and(|old| *old != value) {
+                log!("config: {} overrides `{}`", path.display(), key);
+            }
+            self.root.insert(key, value);
+        }
+        for (name, table) in other.tables {
+            let merged = self.tables.entry(name.clone()).or_default();
+            for (key, value) in table {
+                if merged.get(&key).is_some_and(|old| *old != value) {
+                    log!("config: {} overrides `{}.{}`", path.display(), name, key);
+                }
+                merged.insert(key, value);
+            }
+        }
+        for (name, entries) in other.arrays {
+            let merged = self.arrays.entry(name.clone()).or_default();
+            if name != "commands" {
+                merged.extend(entries);
+                continue;
+            }
+            for entry in entries {
+                let key = command_key(&entry);
+                let existing = key.as_ref().and_then(|key| {
+                    merged
+                        .iter()
+                        .position(|old| command_key(old).as_ref() == Some(key))
+                });
+                match (existing, key) {
+                    (Some(i), Some(key)) => {
+                        log!("config: {} overrides command `{}`", path.display(), key);
+                        merged[i] = entry;
+                    }
+                    _ => merged.push(entry),
+                }
+            }
+        }
+    }
+}
+
+// what `[[commands]]` entries are matched by when merging
+fn command_key(entry: &Table) -> Option<String> {
+    match (entry.get("name"), entry.get("command")) {
+        (Some(Value::String(name)), _) | (None, Some(Value::String(name))) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+pub fn parse_document(source: &str) -> Result<Document, ConfigError> {
+    Parser::new(source).parse()
+}
+
+// where the next `key = value` pair should be stored
+enum Target {
+    Root,
+    Table(String),
+    Array(String),
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Parser<'a> {
+        Parser {
+            chars: source.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ConfigError> {
+        Err(ConfigError::Parse {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.chars.peek() == Some(&'#') {
+            while !matches!(self.chars.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    // whitespace, newlines and comments, used between statements and
+    // between array elements
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.chars.peek() {
+                Some('\n' | '\r') => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), ConfigError> {
+        self.skip_spaces();
+        self.skip_comment();
+        self.eat('\r');
+        match self.chars.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => {
+                let c = *c;
+                self.error(format!("unexpected `{}` after value", c))
+            }
+        }
+    }
+
+    fn parse(mut self) -> Result<Document, ConfigError> {
+        let mut document = Document::default();
+        let mut target = Target::Root;
+
+        loop {
+            self.skip_blank();
+            match self.chars.peek() {
+                None => break,
+                Some('[') => {
+                    self.bump();
+                    let is_array = self.eat('[');
+                    self.skip_spaces();
+                    let name = self.parse_key()?;
+                    self.skip_spaces();
+                    if !self.eat(']') || (is_array && !self.eat(']')) {
+                        return self.error("unterminated table header");
+                    }
+
+                    target = if is_array {
+                        document
+                            .arrays
+                            .entry(name.clone())
+                            .or_default()
+                            .push(Table::new());
+                        Target::Array(name)
+                    } else {
+                        if document.tables.contains_key(&name) {
+                            return self.error(format!("table `{}` defined twice", name));
+                        }
+                        document.tables.insert(name.clone(), Table::new());
+                        Target::Table(name)
+                    };
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let key = self.parse_key()?;
+                    self.skip_spaces();
+                    if !self.eat('=') {
+                        return self.error(format!("expected `=` after `{}`", key));
+                    }
+                    self.skip_spaces();
+                    let value = self.parse_value()?;
+
+                    let table = match &target {
+                        Target::Root => &mut document.root,
+                        Target::Table(name) => document.tables.get_mut(name).unwrap(),
+                        Target::Array(name) => {
+                            document.arrays.get_mut(name).unwrap().last_mut().unwrap()
+                        }
+                    };
+                    if table.contains_key(&key) {
+                        return self.error(format!("duplicate key `{}`", key));
+                    }
+                    table.insert(key, value);
+                    // last, past the newline the line count is one ahead
+                    self.end_of_line()?;
+                }
+            }
+        }
+
+        Ok(document)
+    }
+
+    fn parse_key(&mut self) -> Result<String, ConfigError> {
+        match self.chars.peek() {
+            Some('"') => self.parse_basic_string(),
+            Some('\'') => self.parse_literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        key.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ConfigError> {
+        match self.chars.peek() {
+            Some('"') => Ok(Value::String(self.parse_basic_string()?)),
+            Some('\'') => Ok(Value::String(self.parse_literal_string()?)),
+            Some('[') => self.parse_array(),
+            Some('{') => self.error("inline tables are not supported, use a `[table]` header"),
+            Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+' => self.parse_integer(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    word.push(c);
+                    self.bump();
+                }
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => self.error(format!("unknown value `{}`", word)),
+                }
+            }
+            _ => self.error("expected a value"),
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, ConfigError> {
+        self.bump();
+        let mut value = String::new();
+        loop {
+            // the newline is left for the error to be on the string's line
+            if matches!(self.chars.peek(), None | Some('\n')) {
+                return self.error("unterminated string");
+            }
+            match self.bump() {
+                None => return self.error("unterminated string"),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => value.push(c),
+                            None => return self.error(format!("invalid escape `\\u{}`", hex)),
+                        }
+                    }
+                    Some(c) => return self.error(format!("invalid escape `\\{}`", c)),
+                    None => return self.error("unterminated string"),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, ConfigError> {
+        self.bump();
+        let mut value = String::new();
+        loop {
+            if matches!(self.chars.peek(), None | Some('\n')) {
+                return self.error("unterminated string");
+            }
+            match self.bump() {
+                None => return self.error("unterminated string"),
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<Value, ConfigError> {
+        let mut digits = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || c == '-' || c == '+' {
+                digits.push(c);
+            } else if c != '_' {
+                break;
+            }
+            self.bump();
+        }
+        match digits.parse() {
+            Ok(n) => Ok(Value::Integer(n)),
+            Err(_) => self.error(format!("invalid integer `{}`", digits)),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ConfigError> {
+        self.bump();
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.parse_value()?);
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            if !self.eat(',') {
+                return self.error("expected `,` or `]` in array");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> (usize, String) {
+        match parse_document(source) {
+            Err(ConfigError::Parse { line, message }) => (line, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    fn root_value(source: &str, key: &str) -> Value {
+        parse_document(source).unwrap().root[key].clone()
+    }
+
+    // a file in the temp dir with `contents`, removed again on drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> TempFile {
+            let path = env::temp_dir().join(format!(
+                "command-runner-test-{}-{}.toml",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+
+        fn read(&self) -> String {
+            fs::read_to_string(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn basic_strings_unescape() {
+        assert_eq!(
+            root_value(r#"s = "a\"b\\c\n\t\u00e9""#, "s"),
+            Value::String("a\"b\\c\n\té".into())
+        );
+    }
+
+    #[test]
+    fn literal_strings_keep_backslashes() {
+        assert_eq!(
+            root_value(r"s = 'C:\dir\n # not a comment'", "s"),
+            Value::String(r"C:\dir\n # not a comment".into())
+        );
+    }
+
+    #[test]
+    fn scalars_and_quoted_keys() {
+        let document =
+            parse_document("n = -1_000\nyes = true\nno = false\n\"a key\" = 1\n'b.c' = 2\n")
+                .unwrap();
+        assert_eq!(document.root["n"], Value::Integer(-1000));
+        assert_eq!(document.root["yes"], Value::Boolean(true));
+        assert_eq!(document.root["no"], Value::Boolean(false));
+        assert_eq!(document.root["a key"], Value::Integer(1));
+        assert_eq!(document.root["b.c"], Value::Integer(2));
+    }
+
+    #[test]
+    fn arrays_span_lines_with_comments() {
+        let source = "a = [\n  \"x\", # first\n  'y',\n\n  [1, 2],\n]\nempty = []\n";
+        let document = parse_document(source).unwrap();
+        assert_eq!(
+            document.root["a"],
+            Value::Array(vec![
+                Value::String("x".into()),
+                Value::String("y".into()),
+                Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            ])
+        );
+        assert_eq!(document.root["empty"], Value::Array(Vec::new()));
+    }
+
+    #[test]
+    fn tables_and_arrays_of_tables() {
+        let source = "\
+top = 1
+
+[settings]
+keep = \"error\"
+
+[[commands]]
+command = \"ls\"
+
+[[commands]]
+command = \"pwd\"\r
+name = \"where\"
+";
+        let document = parse_document(source).unwrap();
+        assert_eq!(document.root["top"], Value::Integer(1));
+        assert_eq!(
+            document.tables["settings"]["keep"],
+            Value::String("error".into())
+        );
+        let commands = &document.arrays["commands"];
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0]["command"], Value::String("ls".into()));
+        assert_eq!(commands[1]["name"], Value::String("where".into()));
+    }
+
+    #[test]
+    fn config_reads_the_command_list() {
+        let config = Config::parse(
+            "[[commands]]\ncommand = \"make\"\ntags = [\"#build\"]\n\n[[commands]]\ncommand = \"ls\"\n",
+        )
+        .unwrap();
+        let commands: Vec<&str> = config.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(commands, ["make", "ls"]);
+        assert_eq!(config.commands[0].tags, ["build"]);
+    }
+
+    #[test]
+    fn errors_name_their_line() {
+        assert_eq!(parse_error("a = 1\nb = \"open\n").0, 2);
+        assert_eq!(
+            parse_error("a = 1\n\n# c\na = 2\n"),
+            (4, "duplicate key `a`".into())
+        );
+        assert_eq!(
+            parse_error("[t]\n[t]\n"),
+            (2, "table `t` defined twice".into())
+        );
+        assert_eq!(
+            parse_error("a = [\n1,\n2 3]\n"),
+            (3, "expected `,` or `]` in array".into())
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        for source in [
+            "a",
+            "a 1",
+            "= 1",
+            "a =",
+            "a = 1 2",
+            "a = nope",
+            "a = 1-2",
+            "a = \"\\q\"",
+            "a = \"\\uzzzz\"",
+            "a = 'open",
+            "a = [1",
+            "[t",
+            "[[t]",
+            "a = { b = 1 }",
+        ] {
+            assert!(parse_document(source).is_err(), "{:?} parsed", source);
+        }
+        assert!(parse_error("a = { b = 1 }").1.contains("inline tables"));
+    }
+
+    #[test]
+    fn values_display_as_parseable_toml() {
+        let value = Value::Array(vec![
+            Value::String("quote \" backslash \\ tab \t bell \u{7}".into()),
+            Value::Integer(-3),
+            Value::Boolean(true),
+            Value::Array(Vec::new()),
+        ]);
+        assert_eq!(root_value(&format!("v = {}", value), "v"), value);
+    }
+
+    #[test]
+    fn find_setting_matches_the_whole_statement() {
+        let source = "theme_name = 1\ntheme = [\n 'a', # ]\n \"]\",\n]\nnext = 2\n[t]\nlast = 3\n";
+        let (start, end) = find_setting(source, "theme").unwrap();
+        assert_eq!(&source[start..end], "theme = [\n 'a', # ]\n \"]\",\n]\n");
+        // keys under a table aren't top-level settings
+        assert_eq!(find_setting(source, "last"), None);
+        assert_eq!(find_setting(source, "missing"), None);
+    }
+
+    #[test]
+    fn write_setting_replaces_in_place() {
+        let file = TempFile::new(
+            "replace",
+            "# my config\ntheme = \"dark\" # old\nspinner = \"dots\"\n\n[[commands]]\ncommand = \"ls\"\n",
+        );
+        write_setting(&file.0, "theme", &Value::String("light".into())).unwrap();
+        assert_eq!(
+            file.read(),
+            "# my config\ntheme = \"light\"\nspinner = \"dots\"\n\n[[commands]]\ncommand = \"ls\"\n"
+        );
+        let config = Config::parse(&file.read()).unwrap();
+        assert_eq!(config.theme, theme::Preset::Light);
+        assert_eq!(config.commands.len(), 1);
+    }
+
+    #[test]
+    fn write_setting_adds_missing_keys_on_top() {
+        let file = TempFile::new("add", "[[commands]]\ncommand = \"ls\"\n");
+        let favorites = Value::Array(vec![Value::String("ls".into())]);
+        write_setting(&file.0, "favorites", &favorites).unwrap();
+        write_setting(&file.0, "favorites", &favorites).unwrap();
+        assert_eq!(
+            file.read(),
+            "favorites = [\"ls\"]\n[[commands]]\ncommand = \"ls\"\n"
+        );
+        assert_eq!(Config::parse(&file.read()).unwrap().favorites, ["ls"]);
+    }
+
+    #[test]
+    fn depends_on_can_name_a_command_of_another_file() {
+        let base = TempFile::new(
+            "deps-base",
+            "[[commands]]\nname = \"base\"\ncommand = \"make base\"\n",
+        );
+        let top = TempFile::new(
+            "deps-top",
+            "[[commands]]\nname = \"top\"\ncommand = \"make top\"\ndepends_on = [\"base\"]\n",
+        );
+        let config = Config::load_all(&[base.0.clone(), top.0.clone()]).unwrap();
+        let top_entry = find_command(&config.commands, "top").unwrap();
+        let order: Vec<&str> = run_order(&config.commands, top_entry)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.command.as_str())
+            .collect();
+        assert_eq!(order, ["make base", "make top"]);
+
+        // still checked once merged
+        let err = Config::load_all(std::slice::from_ref(&top.0)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`top` depends on `base`, which isn't a command"));
+    }
+}
@@ -0,0 +1,76 @@
+//! Editing a one-off command in `$VISUAL` / `$EDITOR`.
+//!
+//! The caller is responsible for handing the terminal over (leaving raw mode
+//! and the alternate screen) before calling [`edit`] and taking it back
+//! afterwards.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug)]
+pub enum EditOutcome {
+    Edited(String),
+    /// the file was saved empty (or only whitespace)
+    Empty,
+    /// the editor exited non-zero, which is how most editors signal "abort"
+    Failed(ExitStatus),
+}
+
+fn editor_command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// A fresh path in the temp directory, `kind` says what the file is for.
+pub fn temp_file_path(kind: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    env::temp_dir().join(format!(
+        "command-runner-{}-{}-{}.txt",
+        kind,
+        std::process::id(),
+        nanos
+    ))
+}
+
+/// Opens the editor on a temp file pre-filled with `initial` and returns what
+/// was saved. The editor inherits the real terminal.
+pub fn edit(initial: &str) -> io::Result<EditOutcome> {
+    let path = temp_file_path("command");
+    fs::write(&path, initial)?;
+
+    let result = run_editor(&path);
+    let content = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    let status = result?;
+    if !status.success() {
+        return Ok(EditOutcome::Failed(status));
+    }
+
+    let content = content?;
+    let command = content.trim();
+    if command.is_empty() {
+        Ok(EditOutcome::Empty)
+    } else {
+        Ok(EditOutcome::Edited(command.to_string()))
+    }
+}
+
+fn run_editor(path: &Path) -> io::Result<ExitStatus> {
+    // $EDITOR may carry flags, e.g. `code --wait`
+    let editor = editor_command();
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    Command::new(program).args(parts).arg(path).status()
+}
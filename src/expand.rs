@@ -0,0 +1,223 @@
+//! Environment variables in commands, expanded from our own environment
+//! right before a command is spawned: `$NAME` and `${NAME}` on unix,
+//! `%NAME%` on Windows.
+//!
+//! Text in single quotes is left alone on unix, like the shell does, so
+//! things like `awk '{print $NF}'` reach the program untouched.
+
+use std::{borrow::Cow, env};
+
+/// What happens to a variable that isn't set.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Undefined {
+    /// left in the command as written
+    #[default]
+    Keep,
+    /// replaced by nothing
+    Blank,
+}
+
+impl Undefined {
+    pub const NAMES: &'static [&'static str] = &["keep", "blank"];
+
+    pub fn from_name(name: &str) -> Option<Undefined> {
+        match name {
+            "keep" => Some(Undefined::Keep),
+            "blank" => Some(Undefined::Blank),
+            _ => None,
+        }
+    }
+}
+
+/// Expands the variables in `command` with the current OS's syntax.
+pub fn expand(command: &str, undefined: Undefined) -> String {
+    pieces(command)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.into(),
+            Piece::Variable { name, written } => lookup(name, written, undefined),
+        })
+        .collect()
+}
+
+/// A run of a command: text that is kept as written, or a variable that
+/// [`expand`] replaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Piece<'a> {
+    Text(&'a str),
+    /// `written` is the whole `$NAME`, `${NAME}` or `%NAME%`
+    Variable {
+        name: &'a str,
+        written: &'a str,
+    },
+}
+
+/// `command` split into the pieces [`expand`] sees, with the current OS's
+/// syntax.
+pub fn pieces(command: &str) -> Vec<Piece<'_>> {
+    if cfg!(windows) {
+        windows_pieces(command)
+    } else {
+        unix_pieces(command)
+    }
+}
+
+// the value to put in place of `written`
+fn lookup<'a>(name: &str, written: &'a str, undefined: Undefined) -> Cow<'a, str> {
+    match (env::var(name), undefined) {
+        (Ok(value), _) => value.into(),
+        (Err(_), Undefined::Keep) => written.into(),
+        (Err(_), Undefined::Blank) => "".into(),
+    }
+}
+
+// length of the variable name at the start of `text`
+fn name_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len())
+}
+
+fn unix_pieces(command: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    // inside '...' and "..." respectively
+    let mut quoted = false;
+    let mut double_quoted = false;
+    let mut text_start = 0;
+    let mut at = 0;
+    while let Some(found) = command[at..].find(['$', '\'', '"', '\\']) {
+        let tail = &command[at + found..];
+        let len = match tail.as_bytes()[0] {
+            b'\'' if !double_quoted => {
+                quoted = !quoted;
+                1
+            }
+            b'"' if !quoted => {
+                double_quoted = !double_quoted;
+                1
+            }
+            // the character after a backslash is copied as is
+            b'\\' if !quoted => 1 + tail[1..].chars().next().map_or(0, char::len_utf8),
+            b'$' if !quoted => match variable(tail) {
+                Some((name, len)) => {
+                    if text_start < at + found {
+                        pieces.push(Piece::Text(&command[text_start..at + found]));
+                    }
+                    pieces.push(Piece::Variable {
+                        name,
+                        written: &tail[..len],
+                    });
+                    text_start = at + found + len;
+                    len
+                }
+                None => 1,
+            },
+            // a quote of the other kind, or anything inside single quotes
+            _ => 1,
+        };
+        at += found + len;
+    }
+    if text_start < command.len() {
+        pieces.push(Piece::Text(&command[text_start..]));
+    }
+    pieces
+}
+
+// the name of the `$NAME` or `${NAME}` at the start of `text`, and how long
+// the whole reference is
+fn variable(text: &str) -> Option<(&str, usize)> {
+    if let Some(braced) = text.strip_prefix("${") {
+        let len = name_len(braced);
+        return (len > 0 && braced[len..].starts_with('}')).then(|| (&braced[..len], len + 3));
+    }
+    let len = name_len(&text[1..]);
+    (len > 0).then(|| (&text[1..1 + len], len + 1))
+}
+
+fn windows_pieces(command: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut text_start = 0;
+    let mut at = 0;
+    while let Some(found) = command[at..].find('%') {
+        let start = at + found;
+        let tail = &command[start..];
+        let name = tail[1..].find('%').map(|end| &tail[1..1 + end]);
+        at = match name {
+            Some(name)
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "_-.()".contains(c)) =>
+            {
+                if text_start < start {
+                    pieces.push(Piece::Text(&command[text_start..start]));
+                }
+                let written = &tail[..name.len() + 2];
+                pieces.push(Piece::Variable { name, written });
+                text_start = start + written.len();
+                text_start
+            }
+            // a lone `%`, e.g. in `dir %` or a date format
+            _ => start + 1,
+        };
+    }
+    if text_start < command.len() {
+        pieces.push(Piece::Text(&command[text_start..]));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_variables_outside_single_quotes() {
+        assert_eq!(
+            unix_pieces(r#"a $HOME-${USER}x '$NOT' "$YES" \$NO $ $1"#),
+            [
+                Piece::Text("a "),
+                Piece::Variable {
+                    name: "HOME",
+                    written: "$HOME"
+                },
+                Piece::Text("-"),
+                Piece::Variable {
+                    name: "USER",
+                    written: "${USER}"
+                },
+                Piece::Text(r#"x '$NOT' ""#),
+                Piece::Variable {
+                    name: "YES",
+                    written: "$YES"
+                },
+                Piece::Text(r#"" \$NO $ $1"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_variables_between_percents() {
+        assert_eq!(
+            windows_pieces("dir %USERPROFILE%\\x 100% %%"),
+            [
+                Piece::Text("dir "),
+                Piece::Variable {
+                    name: "USERPROFILE",
+                    written: "%USERPROFILE%"
+                },
+                Piece::Text("\\x 100% %%"),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unset_variables_are_kept_or_blanked() {
+        let command = "echo $COMMAND_RUNNER_TEST_UNSET.";
+        assert_eq!(expand(command, Undefined::Keep), command);
+        assert_eq!(expand(command, Undefined::Blank), "echo .");
+    }
+}
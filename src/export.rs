@@ -0,0 +1,295 @@
+//! `command-runner-tui export [<path>]`: the command list as a standalone
+//! script, a `sh` script on unix and a batch file on Windows, for sharing it
+//! with people who don't use the app.
+//!
+//! The script runs an entry by its number or name, or shows a menu when it
+//! is started without one. Commands run the way [`runner`](crate::runner)
+//! runs them.
+//!
+//! Environment variables are left for the script to expand when it runs,
+//! so their values (often tokens) don't end up in a file that gets shared.
+//! On unix they expand like the app expands them: the value split on
+//! whitespace, never globbed, and an unset variable kept as written unless
+//! `undefined_env = "blank"`. A batch file drops an unset `%NAME%`
+//! whatever `undefined_env` says.
+
+use crate::{
+    config::{CommandEntry, Config},
+    expand::{self, Piece, Undefined},
+    logging::log,
+};
+
+use std::{fs, io, path::Path};
+
+/// Writes the script for the commands of `config` to `path` (the OS's
+/// extension is added when it has none), or prints it when `path` is
+/// `None`. Returns the exit code to exit with.
+pub fn run(config: &Config, path: Option<&Path>) -> i32 {
+    // entries for other operating systems wouldn't run from this script
+    let commands: Vec<&CommandEntry> = config
+        .commands
+        .iter()
+        .filter(|entry| entry.supports_current_os())
+        .collect();
+    let script = if cfg!(windows) {
+        batch_script(&commands)
+    } else {
+        shell_script(&commands, config.undefined_env)
+    };
+
+    let Some(path) = path else {
+        print!("{}", script);
+        return 0;
+    };
+    let path = if path.extension().is_none() {
+        path.with_extension(if cfg!(windows) { "bat" } else { "sh" })
+    } else {
+        path.to_path_buf()
+    };
+    match write_script(&path, &script) {
+        Ok(()) => {
+            log!("exported {} commands to {}", commands.len(), path.display());
+            eprintln!("wrote {} commands to {}", commands.len(), path.display());
+            0
+        }
+        Err(err) => {
+            eprintln!("could not write {}: {}", path.display(), err);
+            1
+        }
+    }
+}
+
+fn write_script(path: &Path, script: &str) -> io::Result<()> {
+    fs::write(path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+// what the menu shows for an entry
+fn label(entry: &CommandEntry) -> String {
+    match (&entry.name, &entry.description) {
+        (Some(name), Some(description)) => format!("{} - {}", name, description),
+        (Some(name), None) => name.clone(),
+        (None, Some(description)) => format!("{} - {}", entry.command, description),
+        (None, None) => entry.command.clone(),
+    }
+}
+
+fn shell_script(commands: &[&CommandEntry], undefined: Undefined) -> String {
+    // the variables are expanded unquoted to be split like the app splits
+    // them, without globbing they can't match files
+    let mut script = String::from(
+        "#!/bin/sh\n\
+         # Exported from command-runner-tui. Run an entry by its number or name,\n\
+         # or start without arguments to pick one from the menu.\n\n\
+         set -f\n\n",
+    );
+    for (i, entry) in commands.iter().enumerate() {
+        let steps: Vec<String> = entry
+            .spawned_commands()
+            .iter()
+            .map(|step| shell_command(step, undefined))
+            .collect();
+        script.push_str(&format!(
+            "cmd_{}() {{\n    {}\n}}\n\n",
+            i + 1,
+            steps.join(" &&\n    ")
+        ));
+    }
+
+    script.push_str("case \"${1:-}\" in\n");
+    for (i, entry) in commands.iter().enumerate() {
+        let mut patterns = vec![(i + 1).to_string()];
+        patterns.extend(entry.name.as_deref().map(shell_quote));
+        script.push_str(&format!("    {}) cmd_{} ;;\n", patterns.join("|"), i + 1));
+    }
+    script.push_str("    \"\")\n");
+    for (i, entry) in commands.iter().enumerate() {
+        script.push_str(&format!(
+            "        echo {}\n",
+            shell_quote(&format!("{}) {}", i + 1, label(entry)))
+        ));
+    }
+    script.push_str(
+        "        printf 'run which? '\n\
+         \x20       read -r choice && [ -n \"$choice\" ] && exec sh \"$0\" \"$choice\"\n\
+         \x20       ;;\n\
+         \x20   *)\n\
+         \x20       echo \"no command $1\" >&2\n\
+         \x20       exit 2\n\
+         \x20       ;;\n\
+         esac\n",
+    );
+    script
+}
+
+// `step` as `sh` code that runs it the way `runner` does. `runner` splits
+// on whitespace without a shell, so every word is quoted to keep `|`, `'`
+// and friends from meaning anything here either, except for the variables
+fn shell_command(step: &str, undefined: Undefined) -> String {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for piece in expand::pieces(step) {
+        match piece {
+            Piece::Variable { name, written } => word.push_str(&match undefined {
+                Undefined::Keep => {
+                    let written = written.replace('$', r"\$").replace('}', r"\}");
+                    format!("${{{}-{}}}", name, written)
+                }
+                Undefined::Blank => format!("${{{}}}", name),
+            }),
+            Piece::Text(text) => {
+                for (i, part) in text.split(char::is_whitespace).enumerate() {
+                    if i > 0 && !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                    if !part.is_empty() {
+                        word.push_str(&shell_quote(part));
+                    }
+                }
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words.join(" ")
+}
+
+// `'...'`, with the quotes inside closed and reopened around an escaped one
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+fn batch_script(commands: &[&CommandEntry]) -> String {
+    let mut script = String::from(
+        "@echo off\r\n\
+         rem Exported from command-runner-tui. Run an entry by its number or name,\r\n\
+         rem or start without arguments to pick one from the menu.\r\n\r\n\
+         set \"choice=%~1\"\r\n\
+         if not \"%choice%\"==\"\" goto run\r\n",
+    );
+    for (i, entry) in commands.iter().enumerate() {
+        script.push_str(&format!(
+            "echo {}) {}\r\n",
+            i + 1,
+            batch_escape(&label(entry), true)
+        ));
+    }
+    script.push_str("set /p \"choice=run which? \"\r\n\r\n:run\r\n");
+    for (i, entry) in commands.iter().enumerate() {
+        let mut names = vec![(i + 1).to_string()];
+        names.extend(entry.name.clone());
+        for name in names {
+            script.push_str(&format!(
+                "if \"%choice%\"==\"{}\" goto cmd_{}\r\n",
+                batch_escape(&name, false),
+                i + 1
+            ));
+        }
+    }
+    script.push_str("echo no command %choice% 1>&2\r\nexit /b 2\r\n");
+
+    for (i, entry) in commands.iter().enumerate() {
+        script.push_str(&format!("\r\n:cmd_{}\r\n", i + 1));
+        // `runner` hands the command to `cmd /C`, so it's written as is,
+        // only a `%` that isn't part of a variable has to be doubled in a
+        // batch file
+        for step in entry.spawned_commands() {
+            let step: String = expand::pieces(&step)
+                .into_iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => batch_escape(text, false),
+                    Piece::Variable { written, .. } => written.to_string(),
+                })
+                .collect();
+            script.push_str(&format!(
+                "{}\r\nif errorlevel 1 exit /b %errorlevel%\r\n",
+                step
+            ));
+        }
+        script.push_str("exit /b 0\r\n");
+    }
+    script
+}
+
+// doubles `%`, and with `echo` also escapes what `cmd` would treat as
+// redirections or command separators
+fn batch_escape(text: &str, echo: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped.push_str("%%"),
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' if echo => {
+                escaped.push('^');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_are_quoted_and_variables_left_to_the_shell() {
+        assert_eq!(
+            shell_command("echo  a|b pre$TOKEN \"x\" '$NF'", Undefined::Blank),
+            r#"'echo' 'a|b' 'pre'${TOKEN} '"x"' ''\''$NF'\'''"#
+        );
+        assert_eq!(
+            shell_command("curl -H ${TOKEN}", Undefined::Keep),
+            r"'curl' '-H' ${TOKEN-\${TOKEN\}}"
+        );
+    }
+
+    // what the exported script and the app run for `command`, as the
+    // arguments `sh` sees
+    #[cfg(unix)]
+    fn script_args(command: &str, undefined: Undefined) -> Vec<String> {
+        let script = format!(
+            "set -f\nprintf '%s\\n' {}",
+            shell_command(command, undefined)
+        );
+        let output = std::process::Command::new("sh")
+            .args(["-c", &script])
+            .env("COMMAND_RUNNER_TEST_TOKEN", "se cr*t")
+            .env_remove("COMMAND_RUNNER_TEST_UNSET")
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_script_expands_like_the_app() {
+        let command = "x --t=$COMMAND_RUNNER_TEST_TOKEN ${COMMAND_RUNNER_TEST_UNSET} \
+                       $COMMAND_RUNNER_TEST_UNSET. '$COMMAND_RUNNER_TEST_TOKEN'";
+        assert_eq!(
+            script_args(command, Undefined::Keep),
+            [
+                "x",
+                "--t=se",
+                "cr*t",
+                "${COMMAND_RUNNER_TEST_UNSET}",
+                "$COMMAND_RUNNER_TEST_UNSET.",
+                "'$COMMAND_RUNNER_TEST_TOKEN'",
+            ]
+        );
+        assert_eq!(
+            script_args(command, Undefined::Blank),
+            ["x", "--t=se", "cr*t", ".", "'$COMMAND_RUNNER_TEST_TOKEN'"]
+        );
+    }
+}
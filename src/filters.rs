@@ -0,0 +1,262 @@
+//! Output filters, applied to every line a command prints before it is
+//! stored. Commands pick them by name with `filters = [...]` in the config.
+
+use std::fmt;
+
+pub trait OutputFilter: fmt::Debug + Send {
+    /// The line to store instead of `line` (which may span several lines),
+    /// or `None` to drop it.
+    fn transform(&self, line: &str) -> Option<String>;
+}
+
+pub const NAMES: &[&str] = &["json-pretty", "strip-ansi"];
+
+pub fn by_name(name: &str) -> Option<Box<dyn OutputFilter>> {
+    match name {
+        "json-pretty" => Some(Box::new(JsonPretty)),
+        "strip-ansi" => Some(Box::new(StripAnsi)),
+        _ => None,
+    }
+}
+
+/// Runs `line` through every filter in order, stopping when one drops it.
+pub fn apply(filters: &[Box<dyn OutputFilter>], line: String) -> Option<String> {
+    filters
+        .iter()
+        .try_fold(line, |line, filter| filter.transform(&line))
+}
+
+/// Removes terminal escape sequences (colors, cursor movement, titles).
+#[derive(Debug)]
+pub struct StripAnsi;
+
+impl OutputFilter for StripAnsi {
+    fn transform(&self, line: &str) -> Option<String> {
+        Some(strip_ansi(line))
+    }
+}
+
+/// `text` without its escape sequences, what [`StripAnsi`] stores and what
+/// copying the clean output puts on the clipboard.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in `@`..=`~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC `\`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // any other escape is two characters long
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Re-indents lines that are a complete JSON document, other lines pass
+/// through untouched.
+#[derive(Debug)]
+pub struct JsonPretty;
+
+impl OutputFilter for JsonPretty {
+    fn transform(&self, line: &str) -> Option<String> {
+        let trimmed = line.trim();
+        if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+            return Some(line.to_string());
+        }
+        let mut printer = JsonPrinter {
+            source: trimmed.as_bytes(),
+            pos: 0,
+            out: String::with_capacity(trimmed.len() * 2),
+        };
+        match printer.value(0) {
+            Some(()) if printer.skip_whitespace() == trimmed.len() => Some(printer.out),
+            _ => Some(line.to_string()),
+        }
+    }
+}
+
+// a recursive descent pass over the JSON that writes it back out indented,
+// returning `None` as soon as the input isn't valid JSON
+struct JsonPrinter<'a> {
+    source: &'a [u8],
+    pos: usize,
+    out: String,
+}
+
+impl JsonPrinter<'_> {
+    fn skip_whitespace(&mut self) -> usize {
+        while self
+            .source
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        self.pos
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.source.get(self.pos).copied()
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        for _ in 0..depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Option<()> {
+        match self.peek()? {
+            b'{' => self.container(depth, b'}', true),
+            b'[' => self.container(depth, b']', false),
+            b'"' => self.string(),
+            _ => self.scalar(),
+        }
+    }
+
+    fn container(&mut self, depth: usize, close: u8, keyed: bool) -> Option<()> {
+        self.out.push(self.source[self.pos] as char);
+        self.pos += 1;
+        if self.peek()? == close {
+            self.pos += 1;
+            self.out.push(close as char);
+            return Some(());
+        }
+        loop {
+            self.newline(depth + 1);
+            if keyed {
+                if self.peek()? != b'"' {
+                    return None;
+                }
+                self.string()?;
+                if self.peek()? != b':' {
+                    return None;
+                }
+                self.pos += 1;
+                self.out.push_str(": ");
+            }
+            self.value(depth + 1)?;
+            match self.peek()? {
+                b',' => {
+                    self.pos += 1;
+                    self.out.push(',');
+                }
+                b if b == close => {
+                    self.pos += 1;
+                    self.newline(depth);
+                    self.out.push(close as char);
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    // copied as written, escapes included
+    fn string(&mut self) -> Option<()> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match *self.source.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        self.out
+            .push_str(std::str::from_utf8(&self.source[start..self.pos]).ok()?);
+        Some(())
+    }
+
+    // numbers, `true`, `false` and `null`
+    fn scalar(&mut self) -> Option<()> {
+        let start = self.pos;
+        while self
+            .source
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.'))
+        {
+            self.pos += 1;
+        }
+        let token = std::str::from_utf8(&self.source[start..self.pos]).ok()?;
+        // `parse` alone would also take `inf` and `NaN`
+        let is_number = token.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+            && token.parse::<f64>().is_ok();
+        let valid = is_number || matches!(token, "true" | "false" | "null");
+        if !valid {
+            return None;
+        }
+        self.out.push_str(token);
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pretty(line: &str) -> String {
+        JsonPretty.transform(line).unwrap()
+    }
+
+    #[test]
+    fn strip_ansi_removes_colors_and_titles() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: x"), "error: x");
+        assert_eq!(strip_ansi("\x1b]0;my title\x07prompt"), "prompt");
+        assert_eq!(strip_ansi("\x1b]2;title\x1b\\prompt"), "prompt");
+        assert_eq!(strip_ansi("done\x1b"), "done");
+    }
+
+    #[test]
+    fn json_is_indented() {
+        assert_eq!(
+            pretty(r#"  {"a": [1, {"b": null}], "c": {}, "d": []}"#),
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}"
+        );
+        // escapes are kept, and a `"` or `}` inside a string ends nothing
+        assert_eq!(
+            pretty(r#"{"msg":"say \"hi\" }\\","n":-1.5e3}"#),
+            "{\n  \"msg\": \"say \\\"hi\\\" }\\\\\",\n  \"n\": -1.5e3\n}"
+        );
+    }
+
+    #[test]
+    fn lines_that_arent_json_pass_through() {
+        for line in [
+            "plain text",
+            "{\"a\": 1} trailing",
+            "{\"a\": }",
+            "[1, 2",
+            "{\"a\" 1}",
+            "[NaN]",
+            "{\"unterminated: 1}",
+        ] {
+            assert_eq!(pretty(line), line);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Fuzzy matching for the command palette: the query's characters have to
+//! appear in order, not necessarily next to each other.
+
+/// Where and how well a query matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// higher is better
+    pub score: i64,
+    /// char indices into the text of the matched characters
+    pub positions: Vec<usize>,
+}
+
+/// Matches `query` against `text`, ignoring case. Runs of consecutive
+/// characters and matches at the start of a word score higher, skipped
+/// characters cost a little. An empty query matches everything.
+pub fn find(query: &str, text: &str) -> Option<Match> {
+    let mut needle = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut found = Match {
+        score: 0,
+        positions: Vec::new(),
+    };
+    let mut previous: Option<char> = None;
+    for (i, c) in text.chars().enumerate() {
+        let Some(&wanted) = needle.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            let word_start = previous.is_none_or(|p| !p.is_alphanumeric());
+            let consecutive = found.positions.last() == Some(&i.wrapping_sub(1));
+            found.score += 1 + if word_start { 3 } else { 0 } + if consecutive { 2 } else { 0 };
+            found.positions.push(i);
+            needle.next();
+        } else if !found.positions.is_empty() {
+            found.score -= 1;
+        }
+        previous = Some(c);
+    }
+    needle.peek().is_none().then_some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, text: &str) -> Option<Vec<usize>> {
+        find(query, text).map(|found| found.positions)
+    }
+
+    fn score(query: &str, text: &str) -> i64 {
+        find(query, text).unwrap().score
+    }
+
+    #[test]
+    fn positions_are_the_matched_chars() {
+        assert_eq!(positions("gst", "git status"), Some(vec![0, 4, 5]));
+        // char indices, not bytes
+        assert_eq!(positions("éd", "héllo wörld"), Some(vec![1, 10]));
+        assert_eq!(positions("", "anything"), Some(vec![]));
+        // spaces in the query are ignored
+        assert_eq!(positions("g s", "git status"), Some(vec![0, 4]));
+    }
+
+    #[test]
+    fn every_char_has_to_match_in_order() {
+        assert_eq!(positions("xyz", "git status"), None);
+        assert_eq!(positions("tg", "git"), None);
+        assert_eq!(positions("gitt", "git"), None);
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert_eq!(positions("GS", "git Status"), Some(vec![0, 4]));
+        assert_eq!(positions("é", "CAFÉ"), Some(vec![3]));
+    }
+
+    #[test]
+    fn word_starts_and_runs_score_higher() {
+        assert!(score("bu", "build") > score("bu", "rebuild"));
+        assert!(score("bd", "bd") > score("bd", "bold"));
+        // every skipped char costs
+        assert!(score("ab", "a_b") > score("ab", "a__b"));
+    }
+}
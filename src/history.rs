@@ -0,0 +1,83 @@
+//! Finished runs, kept for the session so their output (and where it was
+//! scrolled to) can be brought back into the Output pane.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{logging, Stream};
+
+/// How many runs are kept, older ones are dropped.
+pub const LIMIT: usize = 50;
+
+/// Where the Output pane was looking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub scroll: usize,
+    pub hscroll: usize,
+    pub auto_follow: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Run {
+    /// the command as it was run
+    pub command: String,
+    pub output: String,
+    /// one per line of `output`
+    pub stamps: Vec<Duration>,
+    /// one per line of `output` as well
+    pub streams: Vec<Stream>,
+    /// `None` when the exit status couldn't be read
+    pub exit_code: Option<i32>,
+    pub finished_at: SystemTime,
+    pub viewport: Viewport,
+}
+
+/// `12s ago`, `5m ago`, `3h ago`, `2d ago`.
+pub fn format_ago(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// `2024-01-02 14:33` in local time (UTC on Windows).
+pub fn format_absolute(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let local = secs + utc_offset(secs);
+    let (year, month, day) = logging::civil_from_days(local.div_euclid(86_400));
+    let secs_of_day = local.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60
+    )
+}
+
+// seconds the local time zone is ahead of UTC at `secs` since the epoch
+#[cfg(unix)]
+fn utc_offset(secs: i64) -> i64 {
+    let time = secs as libc::time_t;
+    // SAFETY: `tm` is plain data, all zeroes is a valid value
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: plain FFI call, both pointers are valid for the call
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+#[cfg(not(unix))]
+fn utc_offset(_secs: i64) -> i64 {
+    0
+}
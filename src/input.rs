@@ -0,0 +1,196 @@
+//! Single-line text editing with an insertion cursor.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What [`TextInput::handle_key`] did with a key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyEffect {
+    /// the text was edited
+    Changed,
+    /// a cursor move, or an edit with nothing to edit
+    Unchanged,
+    /// not an editing key
+    Ignored,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TextInput {
+    text: String,
+    // byte offset into `text`, always on a char boundary
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// the text left of the cursor, used to place the terminal cursor
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Replaces the text, with the cursor at the end.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Inserts pasted text at the cursor as plain text: line breaks and tabs
+    /// become spaces (a trailing line break is dropped) and other control
+    /// characters are left out, so a paste can't submit or edit anything.
+    pub fn paste(&mut self, text: &str) {
+        for c in text.trim_end_matches(['\r', '\n']).chars() {
+            match c {
+                '\r' | '\n' | '\t' => self.insert(' '),
+                c if c.is_control() => {}
+                c => self.insert(c),
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.before_cursor().chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Ctrl+W: removes trailing whitespace and then the word before the cursor.
+    pub fn delete_word(&mut self) {
+        let before = self.before_cursor().trim_end();
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.before_cursor().chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Applies an editing key. Keys that aren't editing keys are ignored so
+    /// the caller can handle them.
+    pub fn handle_key(&mut self, key: KeyEvent) -> KeyEffect {
+        let before = self.text.len();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.clear(),
+            KeyCode::Char(_) if ctrl => return KeyEffect::Ignored,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Home => self.home(),
+            KeyCode::End => self.end(),
+            _ => return KeyEffect::Ignored,
+        }
+        // every edit changes the length, cursor moves don't
+        if self.text.len() != before {
+            KeyEffect::Changed
+        } else {
+            KeyEffect::Unchanged
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editing_multibyte_text() {
+        let mut input = TextInput::default();
+        input.set("héllo wörld");
+        for _ in 0..3 {
+            input.move_left();
+        }
+        input.insert('ß');
+        assert_eq!(input.as_str(), "héllo wößrld");
+        assert_eq!(input.before_cursor(), "héllo wöß");
+
+        input.delete_word();
+        assert_eq!(input.as_str(), "héllo rld");
+        assert_eq!(input.before_cursor(), "héllo ");
+        // the space before the cursor goes with the word
+        input.delete_word();
+        assert_eq!(input.as_str(), "rld");
+        assert_eq!(input.before_cursor(), "");
+
+        input.set("héllo");
+        input.home();
+        input.move_right();
+        input.move_right();
+        assert_eq!(input.before_cursor(), "hé");
+        input.backspace();
+        input.delete();
+        assert_eq!(input.as_str(), "hlo");
+        assert_eq!(input.before_cursor(), "h");
+    }
+
+    #[test]
+    fn keys_report_what_they_did() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut input = TextInput::default();
+        assert_eq!(
+            input.handle_key(key(KeyCode::Char('ö'))),
+            KeyEffect::Changed
+        );
+        assert_eq!(input.handle_key(key(KeyCode::Left)), KeyEffect::Unchanged);
+        assert_eq!(
+            input.handle_key(key(KeyCode::Backspace)),
+            KeyEffect::Unchanged
+        );
+        assert_eq!(
+            input.handle_key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL)),
+            KeyEffect::Unchanged
+        );
+        assert_eq!(
+            input.handle_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL)),
+            KeyEffect::Ignored
+        );
+        assert_eq!(input.handle_key(key(KeyCode::Enter)), KeyEffect::Ignored);
+        assert_eq!(input.as_str(), "ö");
+    }
+}
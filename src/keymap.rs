@@ -0,0 +1,379 @@
+//! Every key binding, grouped by where it works, for the '?' cheat sheet.
+//!
+//! The keys themselves are matched in `handle_key` and
+//! `App::handle_output_key`. Each group lists the modes it applies to, and a
+//! test checks that every key listed here is handled in each of them.
+
+use crate::AppMode;
+
+/// A key, or a few keys doing the same thing, and what it does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Binding {
+    /// key names like `q`, `ctrl+w`, `down`, `pgup` or `1-9`
+    pub keys: &'static [&'static str],
+    pub action: &'static str,
+}
+
+impl Binding {
+    /// The keys as the cheat sheet shows them, e.g. `j / k / ↓ / ↑`.
+    pub fn label(&self) -> String {
+        self.keys
+            .iter()
+            .map(|&name| match name {
+                "up" => "↑",
+                "down" => "↓",
+                "left" => "←",
+                "right" => "→",
+                name => name,
+            })
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// Bindings under the name of the modes they work in.
+#[derive(Debug)]
+pub struct Group {
+    pub name: &'static str,
+    pub modes: &'static [AppMode],
+    pub bindings: &'static [Binding],
+}
+
+const fn bind(keys: &'static [&'static str], action: &'static str) -> Binding {
+    Binding { keys, action }
+}
+
+const ALL_MODES: &[AppMode] = &[
+    AppMode::Normal,
+    AppMode::Searching,
+    AppMode::FilteringOutput,
+    AppMode::Prompting,
+    AppMode::Confirming,
+    AppMode::EditingCommand,
+    AppMode::History,
+    AppMode::Banner,
+    AppMode::Palette,
+    AppMode::Help,
+    AppMode::Running,
+];
+
+pub const KEYMAP: &[Group] = &[
+    Group {
+        name: "Anywhere",
+        modes: ALL_MODES,
+        bindings: &[bind(&["ctrl+q"], "kill the run and quit right away")],
+    },
+    Group {
+        name: "Command list",
+        modes: &[AppMode::Normal],
+        bindings: &[
+            bind(
+                &["j", "k", "down", "up"],
+                "select the next / previous command",
+            ),
+            bind(&["1-9"], "select the nth command"),
+            bind(&["alt+1-9"], "run the nth command"),
+            bind(&["enter"], "run the selected command"),
+            bind(&["/"], "search the list"),
+            bind(&["ctrl+p"], "go to a command by fuzzy search"),
+            bind(&["i"], "edit the command before running it once"),
+            bind(&["e"], "write a one-off command in $EDITOR"),
+            bind(&["del"], "cancel the selected command if it runs"),
+            bind(&["p"], "pin or unpin the command"),
+            bind(&["s"], "cycle the sort order"),
+            bind(&["!"], "only list commands whose last run failed"),
+            bind(&["y"], "copy the command as it would run"),
+            bind(&["m"], "show the docs of the command's program"),
+            bind(&["H"], "past runs"),
+            bind(&["v"], "switch between stacked and side by side"),
+            bind(&["T"], "next theme"),
+            bind(&["M"], "turn mouse capture on or off"),
+            bind(&["ctrl+r"], "reload the config"),
+            bind(&["&"], "filter the output"),
+            bind(&["P"], "open the output in $PAGER"),
+            bind(&["?"], "this list"),
+            bind(&["esc"], "leave fullscreen output, clear the search"),
+            bind(&["q", "esc"], "quit"),
+        ],
+    },
+    Group {
+        name: "Output (list and running)",
+        modes: &[AppMode::Normal, AppMode::Running],
+        bindings: &[
+            bind(&["pgup", "pgdn"], "scroll half a page"),
+            bind(&["h", "l", "left", "right"], "scroll sideways"),
+            bind(&["f"], "follow new output"),
+            bind(&["w"], "wrap long lines"),
+            bind(&["F"], "turn the output filter on or off"),
+            bind(&["o"], "fullscreen output"),
+            bind(&["t"], "show when each line arrived"),
+            bind(&["#"], "show line numbers"),
+            bind(&["d"], "collapse repeated lines"),
+            bind(&["E"], "cycle all / stderr only / stdout only"),
+            bind(&["c"], "copy the output without escape codes"),
+            bind(&["C"], "copy the output as printed"),
+        ],
+    },
+    Group {
+        name: "Running",
+        modes: &[AppMode::Running],
+        bindings: &[
+            bind(&["esc"], "cancel the run"),
+            bind(&["ctrl+c"], "send SIGINT"),
+            bind(&["x"], "send SIGTERM, SIGKILL if it hangs on"),
+            bind(&["X"], "send SIGKILL"),
+            bind(&["j", "k", "down", "up"], "browse the list"),
+            bind(&["del"], "cancel the run if it's the selected command"),
+            bind(&["M"], "turn mouse capture on or off"),
+            bind(&["?"], "this list"),
+        ],
+    },
+    Group {
+        name: "Search",
+        modes: &[AppMode::Searching],
+        bindings: &[
+            bind(&["down", "up"], "select the next / previous match"),
+            bind(
+                &["enter"],
+                "keep the filter, or run an alias or the top match",
+            ),
+            bind(&["esc"], "clear the search"),
+        ],
+    },
+    Group {
+        name: "Output filter",
+        modes: &[AppMode::FilteringOutput],
+        bindings: &[
+            bind(&["enter"], "keep the filter"),
+            bind(&["esc"], "clear the filter"),
+        ],
+    },
+    Group {
+        name: "Placeholders and inline edit",
+        modes: &[AppMode::Prompting, AppMode::EditingCommand],
+        bindings: &[
+            bind(&["enter"], "next value / run"),
+            bind(&["esc"], "cancel"),
+        ],
+    },
+    Group {
+        name: "Typing",
+        modes: &[
+            AppMode::Searching,
+            AppMode::FilteringOutput,
+            AppMode::Prompting,
+            AppMode::EditingCommand,
+            AppMode::Palette,
+            AppMode::Help,
+        ],
+        bindings: &[
+            bind(&["left", "right"], "move the cursor"),
+            bind(&["home", "end"], "jump to the start / end"),
+            bind(&["backspace", "del"], "delete before / at the cursor"),
+            bind(&["ctrl+w"], "delete the word before the cursor"),
+            bind(&["ctrl+u"], "clear the text"),
+        ],
+    },
+    Group {
+        name: "Confirm",
+        modes: &[AppMode::Confirming],
+        bindings: &[
+            bind(&["y", "enter"], "run (enter twice in a row)"),
+            bind(&["n", "esc"], "don't run"),
+        ],
+    },
+    Group {
+        name: "Palette",
+        modes: &[AppMode::Palette],
+        bindings: &[
+            bind(
+                &["down", "up", "ctrl+n", "ctrl+p"],
+                "select the next / previous command",
+            ),
+            bind(&["enter"], "jump to the command"),
+            bind(&["esc"], "close"),
+        ],
+    },
+    Group {
+        name: "History",
+        modes: &[AppMode::History],
+        bindings: &[
+            bind(&["j", "k", "down", "up"], "select a run"),
+            bind(&["enter"], "show the run's output"),
+            bind(&["t"], "absolute or relative times"),
+            bind(&["esc", "q", "H"], "back to the list"),
+        ],
+    },
+    Group {
+        name: "Error banner",
+        modes: &[AppMode::Banner],
+        bindings: &[
+            bind(&["esc", "enter", "q"], "dismiss"),
+            bind(&["ctrl+r"], "dismiss and reload the config"),
+        ],
+    },
+    Group {
+        name: "Cheat sheet",
+        modes: &[AppMode::Help],
+        bindings: &[
+            bind(&["down", "up", "pgdn", "pgup"], "scroll"),
+            bind(&["esc"], "close"),
+        ],
+    },
+];
+
+/// The groups with the bindings whose keys, action or group name contain
+/// `query`, ignoring case, the ones that work in `mode` first. Groups
+/// without any are left out.
+pub fn matching(query: &str, mode: AppMode) -> Vec<(&'static str, Vec<Binding>)> {
+    let query = query.to_lowercase();
+    let mut groups: Vec<&Group> = KEYMAP.iter().collect();
+    groups.sort_by_key(|group| !group.modes.contains(&mode));
+    groups
+        .into_iter()
+        .filter_map(|group| {
+            let in_group = group.name.to_lowercase().contains(&query);
+            let bindings: Vec<Binding> = group
+                .bindings
+                .iter()
+                .filter(|binding| {
+                    in_group
+                        || binding.keys.iter().any(|name| name.contains(&query))
+                        || binding.label().to_lowercase().contains(&query)
+                        || binding.action.to_lowercase().contains(&query)
+                })
+                .copied()
+                .collect();
+            (!bindings.is_empty()).then_some((group.name, bindings))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handle_key, test_app, App, Confirmation, KeyOutcome};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::time::Instant;
+
+    // the key presses a key name in `Binding::keys` stands for: one, or
+    // one per digit for `1-9`. Empty for a name that isn't a key.
+    fn key_events(name: &str) -> Vec<KeyEvent> {
+        let (modifiers, key) = match name.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            Some(("ctrl", key)) => (KeyModifiers::CONTROL, key),
+            Some(("alt", key)) => (KeyModifiers::ALT, key),
+            Some(_) => return Vec::new(),
+            None => (KeyModifiers::NONE, name),
+        };
+        let codes = match key {
+            "1-9" => ('1'..='9').map(KeyCode::Char).collect(),
+            "enter" => vec![KeyCode::Enter],
+            "esc" => vec![KeyCode::Esc],
+            "del" => vec![KeyCode::Delete],
+            "backspace" => vec![KeyCode::Backspace],
+            "home" => vec![KeyCode::Home],
+            "end" => vec![KeyCode::End],
+            "pgup" => vec![KeyCode::PageUp],
+            "pgdn" => vec![KeyCode::PageDown],
+            "up" => vec![KeyCode::Up],
+            "down" => vec![KeyCode::Down],
+            "left" => vec![KeyCode::Left],
+            "right" => vec![KeyCode::Right],
+            key => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => vec![KeyCode::Char(c)],
+                    _ => Vec::new(),
+                }
+            }
+        };
+        codes
+            .into_iter()
+            .map(|code| KeyEvent::new(code, modifiers))
+            .collect()
+    }
+
+    // an app in `mode`, with whatever the mode works on set up
+    fn app_in(mode: AppMode) -> App {
+        let mut app = test_app(&["true", "echo two"]);
+        match mode {
+            AppMode::Normal => {}
+            AppMode::Searching | AppMode::FilteringOutput | AppMode::Running => app.set_mode(mode),
+            AppMode::Prompting => app.start_prompt(0, vec!["value".into()]),
+            AppMode::Confirming => {
+                app.confirming = Some(Confirmation::Entry(0));
+                app.set_mode(mode);
+            }
+            AppMode::EditingCommand => app.start_inline_edit(),
+            AppMode::History => {
+                app.push_history(Some(0));
+                app.open_history();
+            }
+            AppMode::Banner => app.show_banner("broken".into()),
+            AppMode::Palette => app.open_palette(),
+            AppMode::Help => app.open_help(),
+        }
+        assert_eq!(app.mode, mode);
+        app
+    }
+
+    #[test]
+    fn every_listed_key_is_handled_where_it_is_listed() {
+        for group in KEYMAP {
+            for binding in group.bindings {
+                for name in binding.keys {
+                    let events = key_events(name);
+                    assert!(!events.is_empty(), "`{}` isn't a key name", name);
+                    for &mode in group.modes {
+                        for &key in &events {
+                            let mut app = app_in(mode);
+                            // the second Enter of a double press
+                            app.last_enter = Some(Instant::now());
+                            assert_ne!(
+                                handle_key(&mut app, key),
+                                KeyOutcome::Ignored,
+                                "`{}` ({}) in {:?}",
+                                name,
+                                binding.action,
+                                mode
+                            );
+                            app.kill_all();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unbound_keys_are_ignored() {
+        let f1 = KeyEvent::new(KeyCode::F(1), KeyModifiers::NONE);
+        for mode in [AppMode::Normal, AppMode::Searching, AppMode::Confirming] {
+            assert_eq!(handle_key(&mut app_in(mode), f1), KeyOutcome::Ignored);
+        }
+        // only a plain `e` opens the editor
+        for modifiers in [KeyModifiers::CONTROL, KeyModifiers::ALT] {
+            let mut app = app_in(AppMode::Normal);
+            let key = KeyEvent::new(KeyCode::Char('e'), modifiers);
+            assert_eq!(handle_key(&mut app, key), KeyOutcome::Ignored);
+            assert!(app.terminal_request.is_none());
+        }
+    }
+
+    #[test]
+    fn the_current_modes_keys_come_first() {
+        let groups: Vec<&str> = matching("", AppMode::Running)
+            .into_iter()
+            .map(|(group, _)| group)
+            .collect();
+        assert_eq!(
+            groups[..3],
+            ["Anywhere", "Output (list and running)", "Running"]
+        );
+        let arrows = matching("down", AppMode::Normal);
+        assert!(arrows[0]
+            .1
+            .iter()
+            .any(|binding| binding.label() == "j / k / ↓ / ↑"));
+    }
+}
@@ -0,0 +1,76 @@
+//! Optional debug log for the app itself, enabled with `--log <path>`.
+//!
+//! Every entry is one line prefixed with a UTC timestamp. Until [`init`] is
+//! called all logging is a no-op.
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Opens (appending to) the log file. Calling it more than once keeps the
+/// first file.
+pub fn init(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+    write(format_args!("log opened"));
+    Ok(())
+}
+
+pub fn write(args: fmt::Arguments) {
+    let Some(file) = LOG_FILE.get() else {
+        return;
+    };
+    if let Ok(mut file) = file.lock() {
+        // losing a log line is better than taking the UI down with it
+        let _ = writeln!(file, "{} {}", format_timestamp(SystemTime::now()), args);
+    }
+}
+
+// `YYYY-MM-DDTHH:MM:SS.mmmZ`
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// days since 1970-01-01 to a (year, month, day) date, see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::logging::write(format_args!($($arg)*))
+    };
+}
+
+pub(crate) use log;
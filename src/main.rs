@@ -1,85 +1,621 @@
+mod batch;
+mod cli;
+mod clipboard;
+mod config;
+mod editor;
+mod expand;
+mod export;
+mod filters;
+mod fuzzy;
+mod history;
+mod input;
+mod keymap;
+mod logging;
+mod matcher;
+mod notify;
+mod pager;
+mod progress;
+mod pty;
+mod runner;
+mod signal;
+mod source;
+mod spinner;
+mod state;
+mod template;
+mod theme;
+mod ui;
+
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::{
-    env,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
     error::Error,
-    io::{self, BufRead, BufReader},
-    process::{Command, Stdio},
-    sync::mpsc::{self, Receiver, Sender},
-    thread,
-    time::{Duration, Instant},
+    io::{self, BufRead, BufReader, IsTerminal, Read},
+    iter,
+    ops::ControlFlow,
+    path::PathBuf,
+    process::{self, Child, Stdio},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
 };
 
+use cli::{Cli, Subcommand};
+use config::{CommandEntry, Config, ConfigError, PaneLayout, Value};
+use editor::EditOutcome;
+use filters::OutputFilter;
+use history::{Run, Viewport};
+use input::{KeyEffect, TextInput};
+use logging::log;
+use notify::Notify;
 use ratatui::{
-    prelude::*,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    backend::{Backend, CrosstermBackend},
+    Terminal,
 };
+use signal::Signal;
+use source::{CommandSource, FileCommandSource, StaticCommandSource};
+use spinner::SpinnerStyle;
+use state::State;
+use ui::ui;
+
+// how many output lines can be buffered before the reader threads block,
+// keeps a chatty command from filling memory faster than the UI drains it
+const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+// how long a command gets to exit after SIGTERM before it's killed
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(3);
+// how often the joiner checks whether a child with closed output has exited
+const JOINER_POLL_INTERVAL: Duration = Duration::from_millis(20);
+// a second Enter within this runs a command without confirming it
+const DOUBLE_ENTER_WINDOW: Duration = Duration::from_millis(400);
+// lines PageUp/PageDown move the cheat sheet by
+const HELP_PAGE: isize = 10;
+
+// order of the command list, cycled with 's'
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum SortMode {
+    // as written in the config
+    #[default]
+    Config,
+    Name,
+    // most recently run first
+    Recent,
+    // most often run first
+    Frequent,
+}
+
+impl SortMode {
+    fn next(self) -> SortMode {
+        match self {
+            SortMode::Config => SortMode::Name,
+            SortMode::Name => SortMode::Recent,
+            SortMode::Recent => SortMode::Frequent,
+            SortMode::Frequent => SortMode::Config,
+        }
+    }
 
-#[derive(Debug, PartialEq)]
+    fn name(self) -> &'static str {
+        match self {
+            SortMode::Config => "config",
+            SortMode::Name => "name",
+            SortMode::Recent => "recent",
+            SortMode::Frequent => "frequent",
+        }
+    }
+}
+
+// how often and when a list entry was run this session
+#[derive(Debug, Clone, Copy)]
+struct RunStats {
+    count: usize,
+    last_run: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum AppMode {
     Normal,
     Searching,
+    // typing the pattern the Output pane is filtered by
+    FilteringOutput,
+    // asking for the values of a command's `{placeholders}`
+    Prompting,
+    // waiting for a yes or no on a command marked `confirm = true` or
+    // with a `danger` above low
+    Confirming,
+    // changing the selected command for one run, the config is left alone
+    EditingCommand,
+    // picking a past run to show again
+    History,
+    // an error too important for the status bar, shown until dismissed
+    Banner,
+    // the Ctrl+P overlay for jumping to a command
+    Palette,
+    // the '?' cheat sheet over whatever mode it was opened from
+    Help,
     Running, // used to know when a command is running
 }
 
+impl AppMode {
+    // what the mode badge and the status line call it
+    fn label(self) -> &'static str {
+        match self {
+            AppMode::Normal => "NORMAL",
+            AppMode::Searching => "SEARCH",
+            AppMode::FilteringOutput => "FILTER",
+            AppMode::Prompting => "PROMPT",
+            AppMode::Confirming => "CONFIRM",
+            AppMode::EditingCommand => "EDIT",
+            AppMode::History => "HISTORY",
+            AppMode::Banner => "ERROR",
+            AppMode::Palette => "PALETTE",
+            AppMode::Help => "HELP",
+            AppMode::Running => "RUNNING",
+        }
+    }
+}
+
+// the commands of the current run, spawned one at a time. A plain entry is a
+// chain of one
+#[derive(Debug)]
+struct Steps {
+    commands: Vec<String>,
+    // index of the step that is running
+    current: usize,
+    pty: bool,
+    // each step is a fallback for the one before: the next runs only when
+    // the previous failed, instead of only when it succeeded
+    fallbacks: bool,
+    // printed instead of the usual message when the run ends in failure
+    failure_note: Option<String>,
+    // how often a failing step is run again
+    retries: u32,
+    // retries of the current step so far
+    attempt: u32,
+    // the machine the steps run on over ssh, `None` when they run here
+    host: Option<String>,
+    // the entry's `progress` pattern, looked for in every output line
+    progress: Option<String>,
+    // how the end of the run is announced
+    notify: Notify,
+    // what to write to the stdin of each of `commands`, empty when none
+    // gets any
+    stdin: Vec<Option<String>>,
+}
+
+impl Steps {
+    fn is_chain(&self) -> bool {
+        self.commands.len() > 1
+    }
+
+    fn has_next(&self) -> bool {
+        self.current + 1 < self.commands.len()
+    }
+
+    // the line that explains why the run stopped
+    fn failure(&self, reason: &str) -> String {
+        match &self.failure_note {
+            Some(note) => format!("── {} ──\n", note),
+            None => format!("── {} {}, stopping ──\n", self.describe(), reason),
+        }
+    }
+
+    // `step 2/3: cargo test`
+    fn describe(&self) -> String {
+        format!(
+            "step {}/{}: {}",
+            self.current + 1,
+            self.commands.len(),
+            self.commands[self.current]
+        )
+    }
+}
+
+// collects a value for each `{placeholder}` of a list entry before it runs
+#[derive(Debug)]
+struct Prompt {
+    entry: CommandEntry,
+    index: usize,
+    names: Vec<String>,
+    values: HashMap<String, String>,
+    input: TextInput,
+}
+
+impl Prompt {
+    // the placeholder currently asked for
+    fn name(&self) -> &str {
+        &self.names[self.values.len()]
+    }
+}
+
+// the command text being changed with 'i', run in place of the entry's own
+#[derive(Debug)]
+struct InlineEdit {
+    entry: CommandEntry,
+    input: TextInput,
+}
+
+// a run waiting for 'y' in the Confirm prompt
+#[derive(Debug)]
+enum Confirmation {
+    // a list entry, its placeholders are asked for after
+    Entry(usize),
+    // an entry's command as edited with 'i'
+    Edited(Box<CommandEntry>),
+}
+
+impl Confirmation {
+    fn entry<'a>(&'a self, commands: &'a [CommandEntry]) -> &'a CommandEntry {
+        match self {
+            Confirmation::Entry(idx) => &commands[*idx],
+            Confirmation::Edited(entry) => entry,
+        }
+    }
+}
+
+// the Ctrl+P overlay; the list, search and selection underneath are only
+// touched once an entry is picked
+#[derive(Debug, Default)]
+struct Palette {
+    input: TextInput,
+    // indices into `App::commands`, best match first
+    results: Vec<usize>,
+    // position in `results`
+    selected: usize,
+}
+
+// the '?' overlay, read-only; what it was opened over is shown again when
+// it closes
+#[derive(Debug)]
+struct Help {
+    input: TextInput,
+    // first line shown, clamped when drawn
+    scroll: usize,
+    previous: AppMode,
+}
+
+// what the event loop has to do itself after an event, for the keys that
+// need the real terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TerminalRequest {
+    // hand it to $EDITOR for a one-off command
+    Edit,
+    // show the output in $PAGER
+    Page,
+    ToggleMouse,
+}
+
+// where the event loop's events come from: the terminal, or a list of them
+// when the app is driven without one
+trait EventSource {
+    // the next event, `None` when there was none within `timeout`
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+
+    // whether no event will ever come again, the loop ends after drawing
+    // the screen one last time
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl EventSource for VecDeque<Event> {
+    fn next_event(&mut self, _timeout: Duration) -> io::Result<Option<Event>> {
+        Ok(self.pop_front())
+    }
+
+    fn is_done(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+// a line of `command_output` as the Output pane shows it
+#[derive(Debug, Clone)]
+struct OutputLine<'a> {
+    // 1-based, counted before the output filter
+    number: usize,
+    // when it arrived, since the run started
+    stamp: Duration,
+    // with a count after it when repeats are collapsed
+    text: Cow<'a, str>,
+}
+
+// where an output line came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    // also everything from a pty, which merges the two
+    Stdout,
+    Stderr,
+    // written by the app itself: step headers, retries, failure notes
+    Note,
+}
+
+// which streams the Output pane shows, cycled with 'E'
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum StreamView {
+    #[default]
+    All,
+    Stderr,
+    Stdout,
+}
+
+impl StreamView {
+    fn next(self) -> StreamView {
+        match self {
+            StreamView::All => StreamView::Stderr,
+            StreamView::Stderr => StreamView::Stdout,
+            StreamView::Stdout => StreamView::All,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            StreamView::All => "all",
+            StreamView::Stderr => "stderr only",
+            StreamView::Stdout => "stdout only",
+        }
+    }
+
+    // notes are kept in every view, they say what the lines around them are
+    fn shows(self, stream: Stream) -> bool {
+        match self {
+            StreamView::All => true,
+            StreamView::Stderr => stream != Stream::Stdout,
+            StreamView::Stdout => stream != Stream::Stderr,
+        }
+    }
+}
+
+// what the threads of a run send to the UI
+#[derive(Debug)]
+enum OutputMessage {
+    Line(String, Stream),
+    // sent last, once both readers are done and the child has exited;
+    // `None` when its exit status couldn't be read
+    CommandDone { exit_code: Option<i32> },
+}
+
+// shared with the joiner thread, which reaps it; the UI still needs it for
+// signals and its pid
+type SharedChild = Arc<Mutex<Child>>;
+
+// a poisoned lock only means another thread panicked while holding it, the
+// child handle itself is still usable
+fn lock(child: &SharedChild) -> MutexGuard<'_, Child> {
+    child.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 #[derive(Debug)]
 struct App {
     mode: AppMode,
-    commands: Vec<String>,
+    commands: Vec<CommandEntry>,
+    // alias -> index into `commands`, Enter on a search that is exactly an
+    // alias runs that command
+    aliases: HashMap<String, usize>,
     selected_index: Option<usize>,
     filtered_commands: Vec<usize>,
-    search_input: String,
+    search_input: TextInput,
+    // Enter in the search box runs the first result, like a launcher
+    search_runs_first: bool,
+    // what unset variables in commands expand to
+    undefined_env: expand::Undefined,
+    // for entries without their own `notify_on_complete`
+    notify_on_complete: Notify,
+    // the desktop notification of the last run, until its tool is done
+    notification: Option<notify::Delivery>,
     // for running commands
     command_output: String,
-    output_receiver: Option<Receiver<String>>,
+    // when each line of `command_output` arrived, since the run started
+    output_stamps: Vec<Duration>,
+    // the stream each line of `command_output` came from
+    output_streams: Vec<Stream>,
+    stream_view: StreamView,
+    // show `output_stamps` in front of the lines
+    show_timestamps: bool,
+    // number the output lines, in a gutter left of them
+    show_line_numbers: bool,
+    // show a run of identical output lines as one, with a count
+    collapse_repeats: bool,
+    // first visible output line while frozen
+    output_scroll: usize,
+    // keep the newest output in view, like `less +F`
+    auto_follow: bool,
+    // when off, long lines are cut and scrolled sideways instead
+    wrap_output: bool,
+    output_hscroll: usize,
+    // the Output pane takes the whole screen
+    fullscreen_output: bool,
+    // size of the Output pane at the last draw
+    output_width: u16,
+    output_height: u16,
+    // only output lines containing this are shown while the filter is on;
+    // `command_output` itself is never touched
+    output_filter: TextInput,
+    output_filter_enabled: bool,
+    output_receiver: Option<Receiver<OutputMessage>>,
+    // applied to each line of the current run before it's stored
+    output_filters: Vec<Box<dyn OutputFilter>>,
+    child: Option<SharedChild>,
+    // set after a graceful terminate, the child is killed once it passes
+    kill_deadline: Option<Instant>,
+    // when the failed step of `steps` is run again
+    retry_at: Option<Instant>,
+    running_command: Option<String>,
+    // when the current run started, for the elapsed time in the list
+    started_at: Option<Instant>,
+    steps: Option<Steps>,
+    // list entry being run, `None` for one-off commands from the editor
+    running_index: Option<usize>,
+    // false until the current run emits its first line
+    received_output: bool,
+    // the last percentage found by the `progress` pattern, shown instead
+    // of the spinner
+    progress: Option<f64>,
     last_update: Instant,
     spinner_state: usize,
-    is_windows: bool,
+    spinner: SpinnerStyle,
+    spinner_interval: Duration,
+    // how long the event loop waits for a key before drawing again
+    poll_interval: Duration,
+    pane_layout: PaneLayout,
+    theme: theme::Preset,
+    // pinned commands, by command string
+    favorites: HashSet<String>,
+    // exit code of the last run of each list entry, by command string
+    last_exit: HashMap<String, i32>,
+    // only list entries whose last run failed, on top of the search
+    failed_only: bool,
+    // by command string as well
+    run_stats: HashMap<String, RunStats>,
+    sort_mode: SortMode,
+    config_path: Option<PathBuf>,
+    // the `--config` paths, merged again on every reload
+    config_files: Vec<PathBuf>,
+    // where the selection and search are remembered between sessions
+    state_path: Option<PathBuf>,
+    // asked for the command list on start and on every config reload
+    source: Box<dyn CommandSource>,
+    status_message: Option<String>,
+    prompt: Option<Prompt>,
+    inline_edit: Option<InlineEdit>,
+    // matches the search for lists too big to match between keys
+    filter_worker: Option<matcher::Worker>,
+    confirming: Option<Confirmation>,
+    last_enter: Option<Instant>,
+    // last value entered per placeholder name
+    placeholder_values: HashMap<String, String>,
+    palette: Option<Palette>,
+    help: Option<Help>,
+    // whether the terminal reports mouse events to the app
+    mouse_capture: bool,
+    // left by `run_once` for the event loop
+    terminal_request: Option<TerminalRequest>,
+    // the text of the banner shown in `AppMode::Banner`
+    banner: Option<String>,
+    // finished runs, oldest first
+    history: VecDeque<Run>,
+    // the run whose output is shown, its viewport is saved when switching
+    // to another one
+    viewing_run: Option<usize>,
+    history_selected: usize,
+    // the history shows when runs finished instead of how long ago
+    history_absolute: bool,
 }
 
 impl App {
-    fn new() -> App {
-        let is_windows = env::consts::OS == "windows";
-        // Choose appropriate commands based on OS
-        let commands = if is_windows {
-            vec![
-                "dir".to_string(),
-                "echo \"testing\"".to_string(),
-                "ipconfig".to_string(),
-                "systeminfo".to_string(),
-                "whoami".to_string(),
-                "tasklist".to_string(),
-            ]
-        } else {
-            vec![
-                "ls".to_string(),
-                "echo \"testing\"".to_string(),
-                "ifconfig".to_string(),
-                "uname -a".to_string(),
-                "whoami".to_string(),
-                "ps aux".to_string(),
-            ]
+    fn new(config: Config, source: Box<dyn CommandSource>) -> App {
+        let (commands, status_message) = match source.commands() {
+            Ok(commands) => (commands, None),
+            // the config's commands are the built-in ones when the file
+            // behind the source is broken too
+            Err(err) => (
+                config.commands.clone(),
+                Some(format!("could not load commands: {}", err)),
+            ),
         };
         let filtered_commands = (0..commands.len()).collect();
+        let aliases = index_aliases(&commands);
+        let filter_worker = filter_worker(&commands);
 
-        App {
+        let mut app = App {
             mode: AppMode::Normal,
             commands,
+            aliases,
             filtered_commands,
-            selected_index: Some(0),
-            search_input: String::new(),
+            selected_index: None,
+            search_input: TextInput::default(),
+            search_runs_first: config.search_runs_first,
+            undefined_env: config.undefined_env,
+            notify_on_complete: config.notify_on_complete,
+            notification: None,
             command_output: String::new(),
+            output_stamps: Vec::new(),
+            output_streams: Vec::new(),
+            stream_view: StreamView::default(),
+            show_timestamps: config.timestamps,
+            show_line_numbers: false,
+            collapse_repeats: false,
+            output_filter: TextInput::default(),
+            output_filter_enabled: false,
+            output_scroll: 0,
+            auto_follow: true,
+            wrap_output: true,
+            output_hscroll: 0,
+            fullscreen_output: false,
+            output_width: 0,
+            output_height: 0,
             output_receiver: None,
+            output_filters: Vec::new(),
+            child: None,
+            kill_deadline: None,
+            retry_at: None,
+            running_command: None,
+            started_at: None,
+            steps: None,
+            running_index: None,
+            received_output: false,
+            progress: None,
             last_update: Instant::now(),
             spinner_state: 0,
-            is_windows,
-        }
+            spinner: config.spinner,
+            spinner_interval: config.spinner_interval,
+            poll_interval: config.poll_interval,
+            pane_layout: config.layout,
+            theme: config.theme,
+            favorites: config.favorites.into_iter().collect(),
+            last_exit: HashMap::new(),
+            failed_only: false,
+            run_stats: HashMap::new(),
+            sort_mode: SortMode::default(),
+            config_path: config.path,
+            config_files: Vec::new(),
+            state_path: None,
+            prompt: None,
+            inline_edit: None,
+            filter_worker,
+            confirming: None,
+            last_enter: None,
+            placeholder_values: HashMap::new(),
+            palette: None,
+            help: None,
+            mouse_capture: true,
+            terminal_request: None,
+            banner: None,
+            history: VecDeque::new(),
+            viewing_run: None,
+            history_selected: 0,
+            history_absolute: false,
+            source,
+            status_message,
+        };
+        // floats favorites to the top and selects the first entry
+        app.update_filter();
+        app
     }
 
     fn previous(&mut self) {
+        self.ensure_valid_selection();
         if let Some(current) = self.selected_index {
             if !self.filtered_commands.is_empty() {
                 let current_position = self
@@ -95,6 +631,7 @@ fn previous(&mut self) {
     }
 
     fn next(&mut self) {
+        self.ensure_valid_selection();
         if let Some(current) = self.selected_index {
             if !self.filtered_commands.is_empty() {
                 let current_position = self
@@ -110,282 +647,2394 @@ fn next(&mut self) {
         }
     }
 
+    // 1-based quick-select over the visible list, returns whether there was
+    // an entry at that position
+    fn select_nth(&mut self, n: usize) -> bool {
+        match n.checked_sub(1).and_then(|i| self.filtered_commands.get(i)) {
+            Some(&idx) => {
+                self.selected_index = Some(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // matches the list against the search, right away or, for a big list,
+    // on the worker; its result is picked up by `check_filter`
     fn update_filter(&mut self) {
-        // first store old selection before updating filtered_commands
-        let old_selection = self.selected_index;
+        let query = self.search_input.as_str();
+        match &mut self.filter_worker {
+            Some(worker) => worker.request(query),
+            None => {
+                let matches = matcher::matching(&self.commands, query);
+                self.apply_filter(matches);
+            }
+        }
+    }
 
-        // update filtered commands
-        self.filtered_commands = self
-            .commands
-            .iter()
-            .enumerate()
-            .filter(|(_, cmd)| {
-                cmd.to_lowercase()
-                    .contains(&self.search_input.to_lowercase())
-            })
-            .map(|(i, _)| i)
-            .collect();
+    // matches the list here and now, for callers that go on to use it.
+    // A query still on the worker is dropped, its result would be stale
+    fn filter_now(&mut self) {
+        if let Some(worker) = &mut self.filter_worker {
+            worker.cancel();
+        }
+        let matches = matcher::matching(&self.commands, self.search_input.as_str());
+        self.apply_filter(matches);
+    }
+
+    fn check_filter(&mut self) {
+        if let Some(matches) = self.filter_worker.as_mut().and_then(matcher::Worker::poll) {
+            self.apply_filter(matches);
+        }
+    }
+
+    // the list for the current query, matched here if the worker isn't
+    // done with it yet
+    fn settled_filter(&mut self) -> &[usize] {
+        if self.is_filtering() {
+            self.filter_now();
+        }
+        &self.filtered_commands
+    }
 
-        self.selected_index = if self.filtered_commands.is_empty() {
-            // if no results, temporarily remove selection
-            None
-        } else if let Some(selected) = old_selection {
-            if self.filtered_commands.contains(&selected) {
-                Some(selected)
-            } else {
-                self.filtered_commands.first().copied()
+    // whether the list shown is for an older query
+    fn is_filtering(&self) -> bool {
+        self.filter_worker
+            .as_ref()
+            .is_some_and(matcher::Worker::is_pending)
+    }
+
+    // lists the search matches, narrowed and sorted by the list's settings
+    fn apply_filter(&mut self, matches: Vec<usize>) {
+        let mut filtered: Vec<usize> = matches
+            .into_iter()
+            .filter(|&i| !self.failed_only || self.last_exit(i).is_some_and(|code| code != 0))
+            .collect();
+        match self.sort_mode {
+            SortMode::Config => {}
+            SortMode::Name => filtered.sort_by_key(|&i| self.commands[i].command.to_lowercase()),
+            // never run entries go last, in config order
+            SortMode::Recent => {
+                filtered.sort_by_key(|&i| Reverse(self.run_stats(i).map(|stats| stats.last_run)))
+            }
+            SortMode::Frequent => {
+                filtered.sort_by_key(|&i| Reverse(self.run_stats(i).map_or(0, |stats| stats.count)))
             }
+        }
+        // stable, so favorites and the rest both keep their sorted order
+        filtered.sort_by_key(|&i| !self.is_favorite(i));
+        self.filtered_commands = filtered;
+        self.ensure_valid_selection();
+    }
+
+    // keeps `selected_index` on an entry of `filtered_commands`: the same one
+    // while it's still listed, else the first one, `None` while nothing is.
+    // Called by everything that changes the list or the selection
+    fn ensure_valid_selection(&mut self) {
+        let listed = self
+            .selected_index
+            .is_some_and(|idx| self.filtered_commands.contains(&idx));
+        if !listed {
+            self.selected_index = self.filtered_commands.first().copied();
+        }
+    }
+
+    // the list's state in one line, for the debug bar and the log, e.g.
+    // SEARCH · /ca · 1 of 2 commands · selected `cargo test`
+    fn status_line(&self) -> String {
+        let mut parts = vec![self.mode.label().to_string()];
+        if !self.search_input.is_empty() {
+            parts.push(format!("/{}", self.search_input.as_str()));
+        }
+        if self.failed_only {
+            parts.push("failed only".to_string());
+        }
+        parts.push(if self.is_filtering() {
+            format!("filtering {} commands", self.commands.len())
         } else {
-            self.filtered_commands.first().copied()
+            format!(
+                "{} of {} commands",
+                self.filtered_commands.len(),
+                self.commands.len()
+            )
+        });
+        parts.push(match self.selected_index {
+            Some(idx) => format!("selected `{}`", self.commands[idx].command),
+            None => "nothing selected".to_string(),
+        });
+        parts.join(" · ")
+    }
+
+    // for a run starting or ending on its own: the cheat sheet stays up and
+    // closes into the new mode
+    fn set_run_mode(&mut self, mode: AppMode) {
+        match self.help.as_mut() {
+            Some(help) if self.mode == AppMode::Help => help.previous = mode,
+            _ => self.set_mode(mode),
         }
     }
 
-    fn is_searching(&self) -> bool {
-        self.mode == AppMode::Searching
+    fn set_mode(&mut self, mode: AppMode) {
+        if self.mode != mode {
+            log!("mode {:?} -> {:?}", self.mode, mode);
+            self.mode = mode;
+        }
     }
 
-    fn execute_command(&mut self) -> io::Result<()> {
-        if let Some(idx) = self.selected_index {
-            let command = &self.commands[idx];
-
-            // handle command creation based on the OS
-            let (program, args) = if self.is_windows {
-                ("cmd", vec!["/C", command])
-            } else {
-                let mut parts = command.split_whitespace();
-                let cmd = parts.next().unwrap_or("");
-                let cmd_args: Vec<&str> = parts.collect();
-                (cmd, cmd_args)
-            };
+    // re-reads the config files, keeping the search query and, when the
+    // command still exists, the selection
+    fn reload_config(&mut self) {
+        let config = match Config::load_all(&self.config_files) {
+            Ok(config) => config,
+            Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Config {
+                path: self.config_path.clone(),
+                ..Config::default()
+            },
+            Err(err) => {
+                log!("config reload failed: {}", err);
+                self.status_message = Some(format!("config not reloaded: {}", err));
+                return;
+            }
+        };
 
-            let mut child = Command::new(program)
-                .args(args)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
+        let commands = match self.source.commands() {
+            Ok(commands) => commands,
+            Err(err) => {
+                log!("command list reload failed: {}", err);
+                self.status_message = Some(format!("commands not reloaded: {}", err));
+                return;
+            }
+        };
+        let selected = self
+            .selected_index
+            .map(|idx| self.commands[idx].command.clone());
+        self.commands = commands;
+        self.aliases = index_aliases(&self.commands);
+        self.filter_worker = filter_worker(&self.commands);
+        self.selected_index =
+            selected.and_then(|command| self.commands.iter().position(|e| e.command == command));
+        self.favorites = config.favorites.into_iter().collect();
+        self.spinner = config.spinner;
+        self.spinner_interval = config.spinner_interval;
+        self.poll_interval = config.poll_interval;
+        self.pane_layout = config.layout;
+        self.theme = config.theme;
+        self.show_timestamps = config.timestamps;
+        self.search_runs_first = config.search_runs_first;
+        self.undefined_env = config.undefined_env;
+        self.notify_on_complete = config.notify_on_complete;
+        // a new worker starts with nothing to show, and the old list may
+        // point past the end of the new one
+        self.filter_now();
 
-            let stdout = child.stdout.take().unwrap();
-            let stderr = child.stderr.take().unwrap();
+        log!("config reloaded, {} commands", self.commands.len());
+        self.status_message = Some("config reloaded".into());
+    }
 
-            let (tx, rx) = mpsc::channel();
-            self.output_receiver = Some(rx);
+    fn run_stats(&self, index: usize) -> Option<&RunStats> {
+        self.run_stats.get(&self.commands[index].command)
+    }
 
-            let tx_clone = tx.clone();
-            thread::spawn(move || {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines() {
-                    if let Ok(line) = line {
-                        let _ = tx_clone.send(line);
-                    }
-                }
-            });
+    fn cycle_sort(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+        self.update_filter();
+    }
 
-            thread::spawn(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines() {
-                    if let Ok(line) = line {
-                        let _ = tx.send(format!("Error: {}", line));
-                    }
-                }
+    // list entry `index` was just started
+    fn record_run(&mut self, index: usize) {
+        self.running_index = Some(index);
+        let stats = self
+            .run_stats
+            .entry(self.commands[index].command.clone())
+            .or_insert(RunStats {
+                count: 0,
+                last_run: Instant::now(),
             });
+        stats.count += 1;
+        stats.last_run = Instant::now();
+        if matches!(self.sort_mode, SortMode::Recent | SortMode::Frequent) {
+            self.update_filter();
+        }
+    }
+
+    fn is_favorite(&self, index: usize) -> bool {
+        self.favorites.contains(&self.commands[index].command)
+    }
 
-            self.mode = AppMode::Running;
-            self.command_output.clear();
+    fn toggle_favorite(&mut self) {
+        let Some(idx) = self.selected_index else {
+            return;
+        };
+        let command = self.commands[idx].command.clone();
+        if !self.favorites.remove(&command) {
+            self.favorites.insert(command);
         }
+        self.update_filter();
+        self.save_favorites();
+    }
 
-        Ok(())
+    // the selected entry as it would run: placeholders filled with the last
+    // values entered for them and the way `runner` spawns it spelled out.
+    // Variables stay unexpanded, they may hold secrets
+    fn resolved_command(&self) -> Option<String> {
+        let entry = self
+            .selected_index
+            .map(|idx| self.commands[idx].fill(&self.placeholder_values))?;
+        let steps: Vec<String> = entry
+            .spawned_commands()
+            .iter()
+            .map(|step| runner::invocation(step))
+            .collect();
+        Some(steps.join(" && "))
     }
 
-    fn update_spinner(&mut self) {
-        if Instant::now().duration_since(self.last_update) >= Duration::from_millis(100) {
-            self.spinner_state = (self.spinner_state + 1) % 4;
-            self.last_update = Instant::now()
+    fn copy_command(&mut self) {
+        let Some(command) = self.resolved_command() else {
+            return;
+        };
+        self.status_message = Some(match clipboard::copy(&command) {
+            Ok(()) => format!("copied `{}`", command),
+            // still show it, so it can be copied from the terminal by hand
+            Err(err) => format!("could not copy ({}): {}", err, command),
+        });
+    }
+
+    // 'c' copies the output without escape sequences, for pasting into a
+    // chat or a ticket, 'C' copies it as the command printed it
+    fn copy_output(&mut self, clean: bool) {
+        if self.command_output.is_empty() {
+            self.status_message = Some("no output to copy".into());
+            return;
         }
+        let text = if clean {
+            filters::strip_ansi(&self.command_output)
+        } else {
+            self.command_output.clone()
+        };
+        let lines = text.lines().count();
+        self.status_message = Some(match clipboard::copy(&text) {
+            Ok(()) if clean => format!("copied {} lines of output", lines),
+            Ok(()) => format!("copied {} lines of raw output", lines),
+            Err(err) => format!("could not copy the output: {}", err),
+        });
     }
 
-    fn get_spinner_char(&self) -> &str {
-        match self.spinner_state {
-            0 => "⠋",
-            1 => "⠙",
-            2 => "⠹",
-            3 => "⠸",
-            _ => "⠋",
+    // switches between the stacked and side-by-side layout and remembers the
+    // choice in the config
+    fn toggle_layout(&mut self) {
+        self.pane_layout = self.pane_layout.toggled();
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        let value = Value::String(self.pane_layout.name().to_string());
+        if let Err(err) = config::write_setting(path, "layout", &value) {
+            self.status_message = Some(format!("could not save layout: {}", err));
         }
     }
 
-    fn check_command_output(&mut self) {
-        if let Some(ref receiver) = self.output_receiver {
-            while let Ok(line) = receiver.try_recv() {
-                self.command_output.push_str(&line);
-                self.command_output.push('\n');
+    // 'T': the next built-in theme, shown right away and remembered in the
+    // config
+    fn cycle_theme(&mut self) {
+        self.theme = self.theme.next();
+        self.status_message = Some(format!("theme: {}", self.theme.name()));
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        let value = Value::String(self.theme.name().to_string());
+        if let Err(err) = config::write_setting(path, "theme", &value) {
+            self.status_message = Some(format!("could not save theme: {}", err));
+        }
+    }
+
+    fn save_favorites(&mut self) {
+        let Some(path) = &self.config_path else {
+            self.status_message = Some("no config location, favorites won't be saved".into());
+            return;
+        };
+        // written in list order so the file stays stable between toggles
+        let favorites = self
+            .commands
+            .iter()
+            .filter(|entry| self.favorites.contains(&entry.command))
+            .map(|entry| Value::String(entry.command.clone()))
+            .collect();
+
+        if let Err(err) = config::write_setting(path, "favorites", &Value::Array(favorites)) {
+            self.status_message = Some(format!("could not save favorites: {}", err));
+        }
+    }
+
+    // restores the selection and search of the last session. Falls back to
+    // the first entry when the remembered command is gone from the list
+    fn load_state(&mut self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let state = match state::load(path) {
+            Ok(state) => state,
+            Err(err) => {
+                log!("could not load state from {}: {}", path.display(), err);
+                return;
             }
+        };
+
+        self.search_input.set(&state.search);
+        self.placeholder_values = state.placeholders.into_iter().collect();
+        self.filter_now();
+        self.selected_index = state
+            .selected
+            .and_then(|command| self.commands.iter().position(|e| e.command == command));
+        self.ensure_valid_selection();
+    }
+
+    fn save_state(&self) -> io::Result<()> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        let state = State {
+            selected: self
+                .selected_index
+                .map(|idx| self.commands[idx].command.clone()),
+            search: self.search_input.as_str().to_string(),
+            placeholders: self
+                .placeholder_values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        };
+        state::save(path, &state)
+    }
+
+    fn execute_command(&mut self) -> io::Result<()> {
+        match self.selected_index {
+            Some(idx) => self.execute_entry(idx, false),
+            None => Ok(()),
         }
     }
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // `confirmed` skips the question for entries that ask before running
+    fn execute_entry(&mut self, idx: usize, confirmed: bool) -> io::Result<()> {
+        let entry = &self.commands[idx];
+        if !entry.supports_current_os() {
+            self.status_message = Some(format!(
+                "`{}` is only available on {}",
+                entry.command,
+                entry.os.join(", ")
+            ));
+            return Ok(());
+        }
+        if entry.needs_confirm() && !confirmed {
+            self.confirming = Some(Confirmation::Entry(idx));
+            self.set_mode(AppMode::Confirming);
+            return Ok(());
+        }
+        let names = entry.placeholders();
+        if !names.is_empty() {
+            self.start_prompt(idx, names);
+            return Ok(());
+        }
+        if self.run_command(self.commands[idx].clone())? {
+            self.record_run(idx);
+        }
 
-    // create app state
-    let mut app = App::new();
+        Ok(())
+    }
 
-    loop {
-        if app.mode == AppMode::Running {
-            app.update_spinner();
-            app.check_command_output();
+    // whether this Enter follows the one that asked for confirmation closely
+    // enough to count as a double press
+    fn is_double_enter(&self) -> bool {
+        self.last_enter
+            .is_some_and(|last| last.elapsed() <= DOUBLE_ENTER_WINDOW)
+    }
+
+    fn confirm_command(&mut self) -> io::Result<()> {
+        self.last_enter = None;
+        let Some(confirmation) = self.confirming.take() else {
+            return Ok(());
+        };
+        self.set_mode(AppMode::Normal);
+        match confirmation {
+            Confirmation::Entry(idx) => self.execute_entry(idx, true),
+            Confirmation::Edited(entry) => self.run_command(*entry).map(drop),
         }
+    }
 
-        terminal.draw(|frame| ui(frame, &app))?;
+    fn cancel_confirm(&mut self) {
+        self.confirming = None;
+        self.last_enter = None;
+        self.set_mode(AppMode::Normal);
+    }
 
-        // handle events
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match app.mode {
-                        AppMode::Normal => match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                // close the app
-                                if !app.search_input.is_empty() {
-                                    app.search_input.clear();
-                                    app.update_filter();
-                                } else {
-                                    break;
-                                }
-                            }
-                            KeyCode::Char('j') | KeyCode::Down => app.next(),
-                            KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                            KeyCode::Char('/') => {
-                                app.mode = AppMode::Searching;
-                                // app.search_input.clear();
-                            }
-                            KeyCode::Enter => {
-                                let _ = app.execute_command();
-                            }
-                            _ => {}
-                        },
-                        AppMode::Searching => match key.code {
-                            KeyCode::Esc => {
-                                app.mode = AppMode::Normal;
-                                app.search_input.clear();
-                                app.update_filter();
-                                // if no selection after clearing search, select the first item
-                                if app.selected_index.is_none() && !app.filtered_commands.is_empty()
-                                {
-                                    app.selected_index = Some(app.filtered_commands[0]);
-                                }
-                            }
-                            KeyCode::Enter => {
-                                app.mode = AppMode::Normal;
-                            }
-                            KeyCode::Char(c) => {
-                                app.search_input.push(c);
-                                app.update_filter();
-                            }
-                            KeyCode::Backspace => {
-                                app.search_input.pop();
-                                app.update_filter();
-                            }
-                            _ => {}
-                        },
-                        AppMode::Running => match key.code {
-                            KeyCode::Esc => {
-                                app.mode = AppMode::Normal;
-                                app.output_receiver = None;
-                            }
-                            _ => {}
-                        },
-                    }
-                }
+    fn start_prompt(&mut self, index: usize, names: Vec<String>) {
+        let mut prompt = Prompt {
+            entry: self.commands[index].clone(),
+            index,
+            names,
+            values: HashMap::new(),
+            input: TextInput::default(),
+        };
+        if let Some(last) = self.placeholder_values.get(prompt.name()) {
+            prompt.input.set(last);
+        }
+        self.prompt = Some(prompt);
+        self.set_mode(AppMode::Prompting);
+    }
+
+    // takes the typed value and asks for the next placeholder, or runs the
+    // command once every one has a value
+    fn submit_prompt(&mut self) -> io::Result<()> {
+        let Some(mut prompt) = self.prompt.take() else {
+            return Ok(());
+        };
+        let name = prompt.name().to_string();
+        let value = prompt.input.as_str().to_string();
+        self.placeholder_values.insert(name.clone(), value.clone());
+        prompt.values.insert(name, value);
+
+        if let Some(next) = prompt.names.get(prompt.values.len()) {
+            prompt.input.clear();
+            if let Some(last) = self.placeholder_values.get(next) {
+                prompt.input.set(last);
             }
+            self.prompt = Some(prompt);
+            return Ok(());
+        }
+
+        self.set_mode(AppMode::Normal);
+        if self.run_command(prompt.entry.fill(&prompt.values))? {
+            self.record_run(prompt.index);
         }
+        Ok(())
     }
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture,
-    )?;
+    fn cancel_prompt(&mut self) {
+        self.prompt = None;
+        self.set_mode(AppMode::Normal);
+    }
 
-    terminal.show_cursor()?;
+    // a chain is edited as one `&&` line
+    fn start_inline_edit(&mut self) {
+        let Some(idx) = self.selected_index else {
+            return;
+        };
+        let entry = self.commands[idx].clone();
+        let mut input = TextInput::default();
+        input.set(&entry.step_commands().join(" && "));
+        self.inline_edit = Some(InlineEdit { entry, input });
+        self.set_mode(AppMode::EditingCommand);
+    }
 
-    Ok(())
-}
+    // runs the edited text with the entry's settings (cwd, env, pty, ...)
+    // like a one-off command, so it's not counted as a run of the entry;
+    // `{placeholders}` are run as typed. The entry's `os` and `confirm`
+    // still apply
+    fn submit_inline_edit(&mut self) -> io::Result<()> {
+        let Some(edit) = self.inline_edit.take() else {
+            return Ok(());
+        };
+        self.set_mode(AppMode::Normal);
+        let command = edit.input.as_str().trim();
+        if command.is_empty() {
+            self.status_message = Some("empty command, nothing to run".into());
+            return Ok(());
+        }
+        let entry = CommandEntry {
+            command: command.to_string(),
+            steps: Vec::new(),
+            ..edit.entry
+        };
+        if !entry.supports_current_os() {
+            self.status_message = Some(format!(
+                "`{}` is only available on {}",
+                entry.command,
+                entry.os.join(", ")
+            ));
+            return Ok(());
+        }
+        if entry.needs_confirm() {
+            self.confirming = Some(Confirmation::Edited(Box::new(entry)));
+            self.set_mode(AppMode::Confirming);
+            return Ok(());
+        }
+        self.run_command(entry).map(drop)
+    }
 
-fn ui(frame: &mut Frame, app: &App) {
-    let area = frame.area();
+    fn cancel_inline_edit(&mut self) {
+        self.inline_edit = None;
+        self.set_mode(AppMode::Normal);
+    }
 
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Ratio(1, 3),
-            Constraint::Ratio(2, 3),
-            Constraint::Length(3),
-        ])
-        .split(area);
+    // whether the command was started, it's not when its `depends_on` can't
+    // be resolved
+    fn run_command(&mut self, entry: CommandEntry) -> io::Result<bool> {
+        // `depends_on` comes first, chained like steps so a failing
+        // dependency stops the run; placeholders are already filled in, the
+        // variables are expanded when each step is spawned
+        let (commands, stdin) = match config::run_order(&self.commands, &entry) {
+            Ok(order) => order
+                .iter()
+                .flat_map(|step| {
+                    let commands = step.spawned_commands();
+                    commands
+                        .into_iter()
+                        .map(|command| (command, step.stdin.clone()))
+                })
+                .unzip(),
+            Err(err) => {
+                self.status_message = Some(format!("can't run `{}`: {}", entry.command, err));
+                return Ok(false);
+            }
+        };
+        let steps = Steps {
+            commands,
+            current: 0,
+            pty: entry.pty,
+            fallbacks: false,
+            failure_note: None,
+            retries: entry.retries,
+            attempt: 0,
+            host: entry.host,
+            progress: entry.progress,
+            notify: entry.notify_on_complete.unwrap_or(self.notify_on_complete),
+            stdin,
+        };
+        self.start_run(entry.command, &entry.filters, steps)?;
+        Ok(true)
+    }
 
-    let search_block = Block::default()
-        .title("Search (press '/' to search, 'enter' to navigate in the results)")
-        .borders(Borders::ALL);
+    // shows the docs of the selected command's program in the Output pane:
+    // its man page, or its `--help` when there is none
+    fn open_docs(&mut self) {
+        let Some(idx) = self.selected_index else {
+            return;
+        };
+        let first_step = self.commands[idx].step_commands().swap_remove(0);
+        let Some(program) = first_step
+            .split_whitespace()
+            .next()
+            .and_then(|program| program.rsplit(['/', '\\']).next())
+        else {
+            return;
+        };
 
-    let search_text = format!("/{}", app.search_input);
+        let mut commands = vec![format!("{} --help", program)];
+        if cfg!(unix) {
+            commands.insert(0, format!("man {}", program));
+        }
+        let note = format!("no docs found for `{}`", program);
+        let steps = Steps {
+            commands,
+            current: 0,
+            pty: false,
+            fallbacks: true,
+            failure_note: Some(note.clone()),
+            retries: 0,
+            attempt: 0,
+            host: None,
+            progress: None,
+            notify: Notify::Off,
+            stdin: Vec::new(),
+        };
+        // the note is in the output too, the error is the last fallback's
+        if let Err(err) = self.start_run(format!("docs for {}", program), &[], steps) {
+            log!("{}: {}", note, err);
+            self.status_message = Some(note);
+        }
+    }
 
-    frame.render_widget(Paragraph::new(search_text).block(search_block), layout[0]);
+    fn start_run(&mut self, label: String, filters: &[String], steps: Steps) -> io::Result<()> {
+        self.save_viewport();
+        self.viewing_run = None;
+        self.command_output.clear();
+        self.output_stamps.clear();
+        self.output_streams.clear();
+        self.output_scroll = 0;
+        self.output_hscroll = 0;
+        self.output_filters = filters
+            .iter()
+            .filter_map(|name| filters::by_name(name))
+            .collect();
+        self.running_command = Some(label);
+        self.started_at = Some(Instant::now());
+        self.running_index = None;
+        self.received_output = false;
+        self.progress = None;
+        self.start_steps(steps)
+    }
 
-    let items: Vec<ListItem> = app
-        .filtered_commands
-        .iter()
-        .map(|&index| {
-            let command = &app.commands[index];
-            let display_text = if app.mode == AppMode::Running && Some(index) == app.selected_index
-            {
-                format!("{} {} (running...)", command, app.get_spinner_char())
-            } else {
-                command.clone()
+    // starts the current step, moving on to the next fallback while a step
+    // can't even be spawned
+    fn start_steps(&mut self, mut steps: Steps) -> io::Result<()> {
+        loop {
+            let Err(err) = self.start_step(steps) else {
+                return Ok(());
             };
-            ListItem::new(display_text)
-        })
-        .collect();
-
-    // create list widget
-    let list = List::new(items)
-        .block(Block::default().title("Commands").borders(Borders::ALL))
-        .highlight_style(Style::default().blue())
-        .highlight_symbol(">> ");
-
-    frame.render_stateful_widget(
-        list,
-        layout[1],
-        &mut ratatui::widgets::ListState::default().with_selected(
-            app.filtered_commands
-                .iter()
-                .position(|&idx| Some(idx) == app.selected_index),
-        ),
-    );
-    // command output
-    let output_block = Block::default().title("Output").borders(Borders::ALL);
-
-    frame.render_widget(
-        Paragraph::new(app.command_output.as_str())
-            .block(output_block)
-            .wrap(Wrap { trim: true }),
-        layout[2],
-    );
+            steps = self.steps.take().expect("start_step keeps the steps");
+            if !(steps.fallbacks && steps.has_next()) {
+                if steps.is_chain() || steps.failure_note.is_some() {
+                    let reason = format!("could not be started ({})", err);
+                    self.push_output(&steps.failure(&reason));
+                }
+                return Err(err);
+            }
+            steps.current += 1;
+        }
+    }
+
+    // spawns the current step of `steps`, the output is appended to what
+    // the earlier steps printed
+    fn start_step(&mut self, steps: Steps) -> io::Result<()> {
+        if steps.fallbacks && steps.current > 0 {
+            self.push_output(&format!("── trying {} ──", steps.commands[steps.current]));
+        } else if steps.is_chain() && !steps.fallbacks {
+            self.push_output(&format!("── {} ──", steps.describe()));
+        }
+        let command = steps.commands[steps.current].clone();
+        let stdin = steps.stdin.get(steps.current).cloned().flatten();
+        let use_pty = steps.pty;
+        // `ssh <host> <command>` doesn't need a terminal the way a bare
+        // `ssh` login does
+        let remote = steps.host.is_some();
+        self.steps = Some(steps);
+
+        // expanded only here, so the values of variables don't show up in
+        // the output, the status bar or the log
+        let mut process = runner::build(&expand::expand(&command, self.undefined_env));
+        log!("spawning {}", runner::invocation(&command));
+        let (tx, rx) = mpsc::sync_channel(OUTPUT_CHANNEL_CAPACITY);
+
+        let (child, readers) = if use_pty && pty::available() {
+            let size = (self.output_width, self.output_height);
+            let (child, master) = pty::spawn(process, size)
+                .inspect_err(|err| log!("failed to spawn {:?} in a pty: {}", command, err))?;
+            if stdin.is_some() {
+                // the terminal would echo it back and never send EOF
+                self.status_message = Some("`stdin` isn't written to commands run in a pty".into());
+            }
+            // the terminal merges stdout and stderr into one stream
+            let reader = spawn_reader(master, tx.clone(), Stream::Stdout);
+            (child, vec![reader])
+        } else {
+            if use_pty {
+                self.status_message = Some(pty::unavailable_reason().into());
+            } else if !remote && pty::wants_tty(&command) {
+                self.status_message = Some(format!(
+                    "`{}` may need a terminal, set `pty = true` for it in the config",
+                    command
+                ));
+            }
+
+            let mut child = runner::spawn_with_stdin(
+                process.stdout(Stdio::piped()).stderr(Stdio::piped()),
+                stdin.as_deref(),
+            )
+            .inspect_err(|err| log!("failed to spawn {:?}: {}", command, err))?;
+
+            let stdout = child.stdout.take().unwrap();
+            let stderr = child.stderr.take().unwrap();
+            let readers = vec![
+                spawn_reader(stdout, tx.clone(), Stream::Stdout),
+                spawn_reader(stderr, tx.clone(), Stream::Stderr),
+            ];
+            (child, readers)
+        };
+        log!("spawned pid {}", child.id());
+
+        let child = Arc::new(Mutex::new(child));
+        spawn_joiner(Arc::clone(&child), readers, tx);
+        self.output_receiver = Some(rx);
+        self.child = Some(child);
+        self.kill_deadline = None;
+
+        self.set_run_mode(AppMode::Running);
+
+        Ok(())
+    }
+
+    // the current step is over, starts the next one (if any) or ends the run
+    fn command_done(&mut self, exit_code: Option<i32>) {
+        self.output_receiver = None;
+        self.child = None;
+        self.kill_deadline = None;
+
+        let Some(mut steps) = self.steps.take() else {
+            self.finish_command(exit_code);
+            return;
+        };
+        let notify = steps.notify;
+        let Some(mut exit_code) = exit_code else {
+            self.notify(notify, None);
+            self.finish_command(None);
+            return;
+        };
+        let success = exit_code == 0;
+        if !success && !steps.fallbacks && steps.attempt < steps.retries {
+            steps.attempt += 1;
+            self.push_output(&format!(
+                "── exit code {}, retry {}/{} in {}s ──",
+                exit_code,
+                steps.attempt,
+                steps.retries,
+                runner::RETRY_DELAY.as_secs()
+            ));
+            self.steps = Some(steps);
+            self.retry_at = Some(Instant::now() + runner::RETRY_DELAY);
+            return;
+        }
+        // a chain goes on after a success, fallbacks after a failure
+        if steps.has_next() && success != steps.fallbacks {
+            steps.current += 1;
+            steps.attempt = 0;
+            if self.start_steps(steps).is_ok() {
+                return;
+            }
+            // what a shell reports for a command it can't start
+            exit_code = 127;
+        } else if !success && (steps.is_chain() || steps.failure_note.is_some()) {
+            let reason = format!("failed with exit code {}", exit_code);
+            self.push_output(&steps.failure(&reason));
+        }
+        self.notify(notify, Some(exit_code));
+        self.finish_command(Some(exit_code));
+    }
+
+    // only for runs that ended on their own, a cancelled one was seen ending
+    fn notify(&mut self, notify: Notify, exit_code: Option<i32>) {
+        let command = self.running_command.as_deref().unwrap_or_default();
+        let summary = match exit_code {
+            Some(0) => "succeeded".to_string(),
+            Some(code) => format!("failed with exit code {}", code),
+            None => "ended without an exit code".to_string(),
+        };
+        match notify::send(notify, command, &summary) {
+            Ok(delivery) => self.notification = delivery,
+            Err(err) => {
+                log!("could not notify: {}", err);
+                self.status_message = Some(format!("could not notify: {}", err));
+            }
+        }
+    }
+
+    // reports a desktop notification that failed after it was sent
+    fn check_notification(&mut self) {
+        let Some(result) = self.notification.as_ref().and_then(notify::Delivery::poll) else {
+            return;
+        };
+        self.notification = None;
+        if let Err(err) = result {
+            log!("could not notify: {}", err);
+            self.status_message = Some(format!("could not notify: {}", err));
+        }
+    }
+
+    // the run is over, `exit_code` is `None` when it isn't known
+    fn finish_command(&mut self, exit_code: Option<i32>) {
+        if let (Some(idx), Some(code)) = (self.running_index, exit_code) {
+            self.last_exit
+                .insert(self.commands[idx].command.clone(), code);
+            // a fixed command leaves the failed list right away
+            if self.failed_only {
+                self.update_filter();
+            }
+        }
+        self.push_history(exit_code);
+        self.set_run_mode(AppMode::Normal);
+    }
+
+    fn viewport(&self) -> Viewport {
+        Viewport {
+            scroll: self.output_offset(),
+            hscroll: self.output_hscroll,
+            auto_follow: self.auto_follow,
+        }
+    }
+
+    // remembers where the shown run was scrolled to
+    fn save_viewport(&mut self) {
+        let viewport = self.viewport();
+        if let Some(run) = self.viewing_run.and_then(|i| self.history.get_mut(i)) {
+            run.viewport = viewport;
+        }
+    }
+
+    fn push_history(&mut self, exit_code: Option<i32>) {
+        if self.history.len() == history::LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(Run {
+            command: self.running_command.clone().unwrap_or_default(),
+            output: self.command_output.clone(),
+            stamps: self.output_stamps.clone(),
+            streams: self.output_streams.clone(),
+            exit_code,
+            finished_at: SystemTime::now(),
+            viewport: self.viewport(),
+        });
+        self.viewing_run = Some(self.history.len() - 1);
+    }
+
+    fn open_palette(&mut self) {
+        self.palette = Some(Palette::default());
+        self.update_palette();
+        self.set_mode(AppMode::Palette);
+    }
+
+    // re-ranks the palette results for its current query
+    fn update_palette(&mut self) {
+        let Some(palette) = self.palette.as_mut() else {
+            return;
+        };
+        let query = palette.input.as_str();
+        let mut scored: Vec<(i64, usize)> = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                entry
+                    .searchable_fields()
+                    .filter_map(|field| fuzzy::find(query, field))
+                    .map(|found| found.score)
+                    .max()
+                    .map(|score| (score, i))
+            })
+            .collect();
+        // stable, equal scores stay in config order
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        palette.results = scored.into_iter().map(|(_, i)| i).collect();
+        palette.selected = 0;
+    }
+
+    fn move_palette(&mut self, delta: isize) {
+        if let Some(palette) = self.palette.as_mut() {
+            let last = palette.results.len().saturating_sub(1);
+            palette.selected = palette.selected.saturating_add_signed(delta).min(last);
+        }
+    }
+
+    // selects the picked command in the list, clearing the search when it
+    // hides it
+    fn submit_palette(&mut self) {
+        let picked = self
+            .palette
+            .take()
+            .and_then(|palette| palette.results.get(palette.selected).copied());
+        self.set_mode(AppMode::Normal);
+        let Some(idx) = picked else {
+            return;
+        };
+        if !self.filtered_commands.contains(&idx) {
+            self.search_input.clear();
+            self.filter_now();
+        }
+        self.selected_index = Some(idx);
+        self.ensure_valid_selection();
+    }
+
+    fn close_palette(&mut self) {
+        self.palette = None;
+        self.set_mode(AppMode::Normal);
+    }
+
+    fn open_help(&mut self) {
+        self.help = Some(Help {
+            input: TextInput::default(),
+            scroll: 0,
+            previous: self.mode,
+        });
+        self.set_mode(AppMode::Help);
+    }
+
+    fn close_help(&mut self) {
+        if let Some(help) = self.help.take() {
+            self.set_mode(help.previous);
+        }
+    }
+
+    fn scroll_help(&mut self, delta: isize) {
+        if let Some(help) = self.help.as_mut() {
+            help.scroll = help.scroll.saturating_add_signed(delta);
+        }
+    }
+
+    // pasted text goes into whatever is being typed, or starts a search
+    fn paste(&mut self, text: &str) {
+        let input = match self.mode {
+            AppMode::Normal => {
+                self.set_mode(AppMode::Searching);
+                &mut self.search_input
+            }
+            AppMode::Searching => &mut self.search_input,
+            AppMode::FilteringOutput => &mut self.output_filter,
+            AppMode::Prompting => match self.prompt.as_mut() {
+                Some(prompt) => &mut prompt.input,
+                None => return,
+            },
+            AppMode::EditingCommand => match self.inline_edit.as_mut() {
+                Some(edit) => &mut edit.input,
+                None => return,
+            },
+            AppMode::Palette => match self.palette.as_mut() {
+                Some(palette) => &mut palette.input,
+                None => return,
+            },
+            AppMode::Help => match self.help.as_mut() {
+                Some(help) => &mut help.input,
+                None => return,
+            },
+            _ => return,
+        };
+        input.paste(text);
+        match self.mode {
+            AppMode::Searching => self.update_filter(),
+            AppMode::Palette => self.update_palette(),
+            _ => {}
+        }
+    }
+
+    fn show_banner(&mut self, message: String) {
+        self.banner = Some(message);
+        self.set_mode(AppMode::Banner);
+    }
+
+    fn dismiss_banner(&mut self) {
+        self.banner = None;
+        self.set_mode(AppMode::Normal);
+    }
+
+    fn open_history(&mut self) {
+        if self.history.is_empty() {
+            self.status_message = Some("no finished runs yet".into());
+            return;
+        }
+        // start on the run that is shown, or the latest one
+        self.history_selected = self.viewing_run.unwrap_or(self.history.len() - 1);
+        // the list takes the command list's place, which fullscreen hides
+        self.fullscreen_output = false;
+        self.set_mode(AppMode::History);
+    }
+
+    // brings the output and viewport of the selected run back
+    fn restore_run(&mut self) {
+        self.save_viewport();
+        let Some(run) = self.history.get(self.history_selected) else {
+            return;
+        };
+        self.command_output = run.output.clone();
+        self.output_stamps = run.stamps.clone();
+        self.output_streams = run.streams.clone();
+        self.output_scroll = run.viewport.scroll;
+        self.output_hscroll = run.viewport.hscroll;
+        self.auto_follow = run.viewport.auto_follow;
+        self.viewing_run = Some(self.history_selected);
+        self.set_mode(AppMode::Normal);
+    }
+
+    fn toggle_failed_only(&mut self) {
+        self.failed_only = !self.failed_only;
+        self.update_filter();
+    }
+
+    fn last_exit(&self, index: usize) -> Option<i32> {
+        self.last_exit.get(&self.commands[index].command).copied()
+    }
+
+    // runs whatever came back from the external editor
+    fn handle_edit_outcome(&mut self, outcome: io::Result<EditOutcome>) {
+        let result = match outcome {
+            Ok(EditOutcome::Edited(command)) => {
+                self.run_command(CommandEntry::new(&command)).map(drop)
+            }
+            Ok(EditOutcome::Empty) => {
+                self.status_message = Some("empty command, nothing to run".into());
+                Ok(())
+            }
+            Ok(EditOutcome::Failed(status)) => {
+                self.status_message = Some(format!("editor exited with {}", status));
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            log!("editor run failed: {}", err);
+            self.status_message = Some(format!("failed to run command: {}", err));
+        }
+    }
+
+    fn cancel_command(&mut self) {
+        // drop the receiver first so a reader blocked on a full channel wakes up
+        self.output_receiver = None;
+        let mut exit_code = None;
+        if let Some(child) = self.child.take() {
+            let mut child = lock(&child);
+            // killing closes the pipes, which ends the reader threads
+            let _ = child.kill();
+            exit_code = match child.wait() {
+                Ok(status) => {
+                    log!("cancelled pid {}, {}", child.id(), status);
+                    Some(runner::exit_code(status))
+                }
+                Err(err) => {
+                    log!("failed to reap pid {}: {}", child.id(), err);
+                    None
+                }
+            };
+        }
+        self.kill_deadline = None;
+        self.retry_at = None;
+        self.steps = None;
+        self.finish_command(exit_code);
+    }
+
+    // 'del' in the list: cancels the run of the selected entry, the one
+    // with the spinner, so the list can be browsed while it runs
+    fn cancel_selected(&mut self) {
+        let Some(idx) = self.selected_index else {
+            return;
+        };
+        if self.in_flight() && self.running_index == Some(idx) {
+            self.cancel_command();
+        } else {
+            self.status_message = Some(format!("`{}` isn't running", self.commands[idx].command));
+        }
+    }
+
+    // Ctrl+Q: kills whatever runs without asking, before the app exits. The
+    // child is reaped here, so it's never left behind as an orphan, and its
+    // reader threads end with the pipes it closes
+    fn kill_all(&mut self) {
+        if self.in_flight() || self.child.is_some() {
+            log!("killing the run before quitting");
+            self.cancel_command();
+        }
+    }
+
+    fn signal_command(&mut self, signal: Signal) {
+        let Some(child) = &self.child else {
+            return;
+        };
+        let mut child = lock(child);
+        let pid = child.id();
+        // sent with the lock held, so the joiner can't reap the child (and
+        // free its pid) in between the exit check and the signal
+        let result = signal::send(&mut child, signal);
+        drop(child);
+        self.status_message = Some(match result {
+            Ok(()) => {
+                log!("sent {} to pid {}", signal.name(), pid);
+                format!("sent {} to pid {}", signal.name(), pid)
+            }
+            Err(err) => {
+                log!("failed to send {} to pid {}: {}", signal.name(), pid, err);
+                format!("could not send {} to pid {}: {}", signal.name(), pid, err)
+            }
+        });
+    }
+
+    // SIGTERM now, SIGKILL if the command is still around after the timeout
+    fn terminate_command(&mut self) {
+        self.signal_command(Signal::Terminate);
+        self.kill_deadline = Some(Instant::now() + TERMINATE_TIMEOUT);
+    }
+
+    // runs the failed step again once the retry delay is over
+    fn check_retry(&mut self) {
+        if self.retry_at.is_none_or(|at| Instant::now() < at) {
+            return;
+        }
+        self.retry_at = None;
+        let Some(steps) = self.steps.take() else {
+            return;
+        };
+        log!(
+            "retry {}/{} of {:?}",
+            steps.attempt,
+            steps.retries,
+            steps.commands[steps.current]
+        );
+        if self.start_steps(steps).is_err() {
+            self.finish_command(Some(127));
+        }
+    }
+
+    fn check_kill_deadline(&mut self) {
+        if self
+            .kill_deadline
+            .is_none_or(|deadline| Instant::now() < deadline)
+        {
+            return;
+        }
+        self.kill_deadline = None;
+        let still_running = self
+            .child
+            .as_ref()
+            .is_some_and(|child| matches!(lock(child).try_wait(), Ok(None)));
+        if still_running {
+            self.signal_command(Signal::Kill);
+        }
+    }
+
+    // a run has output coming or a retry pending, whatever the mode; once
+    // a run can go on in the background its output still has to be drained
+    fn in_flight(&self) -> bool {
+        self.output_receiver.is_some() || self.retry_at.is_some()
+    }
+
+    // the per-frame work of a run in flight
+    fn tick(&mut self) {
+        self.update_spinner();
+        self.check_command_output();
+        self.check_kill_deadline();
+        self.check_retry();
+    }
+
+    fn update_spinner(&mut self) {
+        if Instant::now().duration_since(self.last_update) >= self.spinner_interval {
+            self.spinner_state = (self.spinner_state + 1) % self.spinner.frames().len();
+            self.last_update = Instant::now()
+        }
+    }
+
+    fn get_spinner_char(&self) -> &str {
+        let frames = self.spinner.frames();
+        frames[self.spinner_state % frames.len()]
+    }
+
+    // what shows the run is alive: the progress once the output reported
+    // some, the spinner until then
+    fn activity(&self) -> String {
+        match self.progress {
+            Some(percent) => format!("{:.0}%", percent),
+            None => self.get_spinner_char().to_string(),
+        }
+    }
+
+    // matched before the output filters, which may change the line, but
+    // without colors, which would get in the way of the pattern
+    fn update_progress(&mut self, line: &str) {
+        let Some(pattern) = self
+            .steps
+            .as_ref()
+            .and_then(|steps| steps.progress.as_deref())
+        else {
+            return;
+        };
+        if let Some(percent) = progress::parse(pattern, &filters::strip_ansi(line)) {
+            self.progress = Some(percent);
+        }
+    }
+
+    // the filter pattern, if lines are currently being filtered by it
+    fn active_output_filter(&self) -> Option<&str> {
+        Some(self.output_filter.as_str())
+            .filter(|pattern| self.output_filter_enabled && !pattern.is_empty())
+    }
+
+    // the app's own lines, see `Stream::Note`
+    fn push_output(&mut self, text: &str) {
+        self.push_lines(text, Stream::Note);
+    }
+
+    // appends `text` to the output, every line of it stamped with the time
+    // it was drained at
+    fn push_lines(&mut self, text: &str, stream: Stream) {
+        let stamp = self.started_at.map_or(Duration::ZERO, |at| at.elapsed());
+        for line in text.strip_suffix('\n').unwrap_or(text).split('\n') {
+            self.command_output.push_str(line);
+            self.command_output.push('\n');
+            self.output_stamps.push(stamp);
+            self.output_streams.push(stream);
+        }
+    }
+
+    // the output lines the Output pane shows, all of them or the ones
+    // matching the output filter (case-insensitive) from the streams in
+    // view. With `collapse_repeats` a run of identical lines is shown once,
+    // as its first line
+    fn visible_output_lines(&self) -> impl Iterator<Item = OutputLine<'_>> {
+        let pattern = self.active_output_filter().map(str::to_lowercase);
+        let collapse = self.collapse_repeats;
+        let view = self.stream_view;
+        let stamps = self
+            .output_stamps
+            .iter()
+            .copied()
+            .chain(iter::repeat(Duration::ZERO));
+        let streams = self
+            .output_streams
+            .iter()
+            .copied()
+            .chain(iter::repeat(Stream::Stdout));
+        let mut lines = stamps
+            .zip(streams)
+            .zip(self.command_output.lines())
+            .enumerate()
+            .filter(move |(_, ((_, stream), _))| view.shows(*stream))
+            .peekable();
+        iter::from_fn(move || {
+            let (i, ((stamp, _), text)) = lines.next()?;
+            let mut repeats = 1;
+            while collapse && lines.next_if(|(_, (_, next))| *next == text).is_some() {
+                repeats += 1;
+            }
+            Some(OutputLine {
+                number: i + 1,
+                stamp,
+                text: if repeats > 1 {
+                    Cow::Owned(format!("{} (x{})", text, repeats))
+                } else {
+                    Cow::Borrowed(text)
+                },
+            })
+        })
+        .filter(move |line| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| line.text.to_lowercase().contains(pattern))
+        })
+    }
+
+    // digits of the widest line number, `None` while they're hidden
+    fn line_number_width(&self) -> Option<usize> {
+        // one stamp per line, cheaper than counting them
+        self.show_line_numbers
+            .then(|| self.output_stamps.len().max(1).to_string().len())
+    }
+
+    fn toggle_output_filter(&mut self) {
+        if self.output_filter.is_empty() {
+            self.status_message = Some("no output filter set, press '&' to set one".to_string());
+        } else {
+            self.output_filter_enabled = !self.output_filter_enabled;
+        }
+    }
+
+    // rows the visible output takes up in the Output pane, which is more
+    // than its lines once long ones wrap
+    fn output_rows(&self) -> usize {
+        if !self.wrap_output {
+            return self.visible_output_lines().count();
+        }
+        let width = self.output_width as usize;
+        let numbers = self.line_number_width();
+        self.visible_output_lines()
+            .map(|line| {
+                let gutter = ui::gutter(&line, numbers, self.show_timestamps);
+                if gutter.is_empty() {
+                    ui::wrapped_rows(&line.text, width)
+                } else {
+                    ui::wrapped_rows(&format!("{}{}", gutter, line.text), width)
+                }
+            })
+            .sum()
+    }
+
+    // scroll offsets count rows, so they match what `Paragraph::scroll`
+    // expects when wrapping
+    fn max_output_scroll(&self) -> usize {
+        self.output_rows()
+            .saturating_sub(self.output_height as usize)
+    }
+
+    // after the pane changed size: a frozen view that now has less to scroll
+    // through stays in range, and so does the column
+    fn clamp_output_scroll(&mut self, rows: usize) {
+        let height = self.output_height as usize;
+        self.output_scroll = self.output_scroll.min(rows.saturating_sub(height));
+        self.output_hscroll = self.output_hscroll.min(self.max_output_hscroll());
+    }
+
+    // the offset the Output pane is drawn at
+    fn output_offset(&self) -> usize {
+        if self.auto_follow {
+            self.max_output_scroll()
+        } else {
+            self.output_scroll.min(self.max_output_scroll())
+        }
+    }
+
+    fn scroll_output(&mut self, delta: isize) {
+        // scrolling while following freezes the view where it currently is
+        self.output_scroll = self.output_offset();
+        self.auto_follow = false;
+        self.output_scroll = self
+            .output_scroll
+            .saturating_add_signed(delta)
+            .min(self.max_output_scroll());
+    }
+
+    fn max_output_hscroll(&self) -> usize {
+        self.visible_output_lines()
+            .map(|line| line.text.chars().count())
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(self.output_width as usize)
+    }
+
+    fn scroll_output_horizontally(&mut self, delta: isize) {
+        if !self.wrap_output {
+            self.output_hscroll = self
+                .output_hscroll
+                .saturating_add_signed(delta)
+                .min(self.max_output_hscroll());
+        }
+    }
+
+    fn toggle_wrap(&mut self) {
+        self.wrap_output = !self.wrap_output;
+        self.output_hscroll = 0;
+    }
+
+    fn toggle_follow(&mut self) {
+        self.output_scroll = self.output_offset();
+        self.auto_follow = !self.auto_follow;
+    }
+
+    // keys that act on the Output pane, shared by the modes that show it.
+    // Returns whether the key was used
+    fn handle_output_key(&mut self, code: KeyCode) -> bool {
+        let half_page = (self.output_height as isize / 2).max(1);
+        let half_width = (self.output_width as isize / 2).max(1);
+        match code {
+            KeyCode::PageUp => self.scroll_output(-half_page),
+            KeyCode::PageDown => self.scroll_output(half_page),
+            KeyCode::Left | KeyCode::Char('h') => self.scroll_output_horizontally(-half_width),
+            KeyCode::Right | KeyCode::Char('l') => self.scroll_output_horizontally(half_width),
+            KeyCode::Char('f') => self.toggle_follow(),
+            KeyCode::Char('w') => self.toggle_wrap(),
+            KeyCode::Char('F') => self.toggle_output_filter(),
+            KeyCode::Char('o') => self.fullscreen_output = !self.fullscreen_output,
+            KeyCode::Char('t') => self.show_timestamps = !self.show_timestamps,
+            KeyCode::Char('#') => self.show_line_numbers = !self.show_line_numbers,
+            KeyCode::Char('d') => self.collapse_repeats = !self.collapse_repeats,
+            KeyCode::Char('E') => self.stream_view = self.stream_view.next(),
+            KeyCode::Char('c') => self.copy_output(true),
+            KeyCode::Char('C') => self.copy_output(false),
+            _ => return false,
+        }
+        true
+    }
+
+    // the pid of the running child, for the status bar
+    fn child_pid(&self) -> Option<u32> {
+        self.child.as_ref().map(|child| lock(child).id())
+    }
+
+    // the command whose alias the search query is, exactly
+    fn alias_hit(&self) -> Option<usize> {
+        self.aliases.get(self.search_input.as_str().trim()).copied()
+    }
+
+    // Enter in the search box: an alias runs its command straight away,
+    // anything else just leaves the box to navigate the results
+    fn submit_search(&mut self) -> io::Result<()> {
+        let idx = match self.alias_hit() {
+            Some(idx) => {
+                log!(
+                    "search {:?} is an alias of {:?}",
+                    self.search_input.as_str(),
+                    self.commands[idx].command
+                );
+                idx
+            }
+            // with nothing to run the query stays up to be fixed
+            None if self.search_runs_first => match self.settled_filter().first() {
+                Some(&idx) => idx,
+                None => return Ok(()),
+            },
+            None => {
+                self.set_mode(AppMode::Normal);
+                return Ok(());
+            }
+        };
+        self.set_mode(AppMode::Normal);
+        self.selected_index = Some(idx);
+        self.last_enter = Some(Instant::now());
+        self.execute_command()
+    }
+
+    fn check_command_output(&mut self) {
+        // taken out while draining, the lines are pushed through `self`
+        let Some(receiver) = self.output_receiver.take() else {
+            return;
+        };
+        // drain at most one channel's worth per tick so a fast producer
+        // can't keep us in this loop forever
+        let mut done = None;
+        for _ in 0..OUTPUT_CHANNEL_CAPACITY {
+            match receiver.try_recv() {
+                Ok(OutputMessage::Line(line, stream)) => {
+                    self.received_output = true;
+                    self.update_progress(&line);
+                    if let Some(line) = filters::apply(&self.output_filters, line) {
+                        self.push_lines(&line, stream);
+                    }
+                }
+                Ok(OutputMessage::CommandDone { exit_code }) => {
+                    done = Some(exit_code);
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                // the joiner always says goodbye first, unless it panicked
+                Err(TryRecvError::Disconnected) => {
+                    done = Some(None);
+                    break;
+                }
+            }
+        }
+        self.output_receiver = Some(receiver);
+        if let Some(exit_code) = done {
+            self.command_done(exit_code);
+        }
+    }
+}
+
+// splits a search query into its `tag:<name>` terms (the names, `#` allowed
+// in front) and the free text around them
+fn search_terms(query: &str) -> (Vec<&str>, String) {
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+    for word in query.split_whitespace() {
+        match word.strip_prefix("tag:") {
+            Some(tag) => tags.push(tag.trim_start_matches('#')),
+            None => words.push(word),
+        }
+    }
+    (tags, words.join(" "))
+}
+
+// a worker with a snapshot of `commands`, when there are enough of them
+fn filter_worker(commands: &[CommandEntry]) -> Option<matcher::Worker> {
+    (commands.len() >= matcher::WORKER_THRESHOLD).then(|| matcher::Worker::spawn(commands.into()))
+}
+
+// the first command with an alias wins when several share it
+fn index_aliases(commands: &[CommandEntry]) -> HashMap<String, usize> {
+    let mut aliases = HashMap::new();
+    for (idx, entry) in commands.iter().enumerate() {
+        for alias in &entry.aliases {
+            aliases.entry(alias.clone()).or_insert(idx);
+        }
+    }
+    aliases
+}
+
+// forwards each line of `reader` to the output channel, stopping as soon as
+// the receiver is gone so the thread never outlives the run
+fn spawn_reader<R: Read + Send + 'static>(
+    reader: R,
+    tx: SyncSender<OutputMessage>,
+    stream: Stream,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            // raw bytes rather than `lines()`, which errors out on (and so
+            // drops) any line that isn't valid UTF-8. What was read before an
+            // error stays in `buf`, so a last line without a newline is still
+            // sent when the pipe fails instead of closing
+            let closed = match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => true,
+                Ok(_) => false,
+                // keeps the partial line and reads on
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // e.g. EIO from a pty master once the child is gone
+                Err(_) => true,
+            };
+            if buf.is_empty() {
+                break;
+            }
+            let mut line = runner::decode_line(&buf);
+            buf.clear();
+            if stream == Stream::Stderr {
+                line.insert_str(0, "Error: ");
+            }
+            if tx.send(OutputMessage::Line(line, stream)).is_err() || closed {
+                break;
+            }
+        }
+    })
+}
+
+// waits for the readers to drain the child's output and for the child to
+// exit, then reports the exit code after the last line
+fn spawn_joiner(child: SharedChild, readers: Vec<JoinHandle<()>>, tx: SyncSender<OutputMessage>) {
+    thread::spawn(move || {
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let pid = lock(&child).id();
+        // polled rather than `wait()`ed for, so the lock is free for signals
+        // if the child closed its output but keeps running
+        let exit_code = loop {
+            let status = lock(&child).try_wait();
+            match status {
+                Ok(Some(status)) => {
+                    log!("pid {} finished, {}", pid, status);
+                    break Some(runner::exit_code(status));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    log!("failed to wait for pid {}: {}", pid, err);
+                    break None;
+                }
+            }
+            thread::sleep(JOINER_POLL_INTERVAL);
+        };
+        // the receiver is gone when the run was cancelled, nobody to tell
+        let _ = tx.send(OutputMessage::CommandDone { exit_code });
+    });
+}
+
+// processes a single event, `Break` when the app should quit. The keys
+// that need the real terminal only leave a `TerminalRequest` behind, so the
+// app can be driven by a list of events without one
+fn run_once(app: &mut App, event: Event) -> ControlFlow<()> {
+    match event {
+        Event::Paste(text) => app.paste(&text),
+        Event::Key(key) if key.kind == KeyEventKind::Press => {
+            // `--log` follows what the keys do to the list
+            let before = app.status_line();
+            let outcome = handle_key(app, key);
+            let after = app.status_line();
+            if after != before {
+                log!("{}", after);
+            }
+            if outcome == KeyOutcome::Quit {
+                return ControlFlow::Break(());
+            }
+        }
+        // the loop draws right after every event, which lays the screen
+        // out for the new size; scroll offsets are clamped when drawn
+        Event::Resize(width, height) => log!("terminal resized to {}x{}", width, height),
+        _ => {}
+    }
+    ControlFlow::Continue(())
+}
+
+// what `handle_key` did with a key
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyOutcome {
+    Handled,
+    // nothing is bound to it in the current mode
+    Ignored,
+    Quit,
+}
+
+// a key press, handled by what the current mode does with it
+fn handle_key(app: &mut App, key: KeyEvent) -> KeyOutcome {
+    // a status message stays up until the next key press
+    app.status_message = None;
+    // quits from any mode, a run included
+    if key.code == KeyCode::Char('q') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.kill_all();
+        return KeyOutcome::Quit;
+    }
+    match app.mode {
+        AppMode::Normal => match key.code {
+            KeyCode::Esc if app.fullscreen_output => {
+                app.fullscreen_output = false;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                // close the app
+                if !app.search_input.is_empty() {
+                    app.search_input.clear();
+                    app.update_filter();
+                } else {
+                    return KeyOutcome::Quit;
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => app.next(),
+            KeyCode::Char(c @ '1'..='9') => {
+                let n = c.to_digit(10).unwrap_or_default() as usize;
+                // Alt+<n> runs the entry right away
+                if app.select_nth(n) && key.modifiers.contains(KeyModifiers::ALT) {
+                    if let Err(err) = app.execute_command() {
+                        app.status_message = Some(format!("failed to run command: {}", err));
+                    }
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => app.previous(),
+            KeyCode::Char('/') => {
+                app.set_mode(AppMode::Searching);
+                // app.search_input.clear();
+            }
+            KeyCode::Char('&') => {
+                app.output_filter_enabled = true;
+                app.set_mode(AppMode::FilteringOutput);
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.open_palette()
+            }
+            KeyCode::Char('p') => app.toggle_favorite(),
+            KeyCode::Char('v') => app.toggle_layout(),
+            KeyCode::Char('T') => app.cycle_theme(),
+            KeyCode::Char('s') => app.cycle_sort(),
+            KeyCode::Char('!') => app.toggle_failed_only(),
+            KeyCode::Char('y') => app.copy_command(),
+            KeyCode::Char('m') => app.open_docs(),
+            KeyCode::Char('H') => app.open_history(),
+            KeyCode::Char('M') => app.terminal_request = Some(TerminalRequest::ToggleMouse),
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.reload_config()
+            }
+            // a plain `e` only, ctrl+e is too easy to press out of habit
+            KeyCode::Char('e')
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                app.terminal_request = Some(TerminalRequest::Edit)
+            }
+            KeyCode::Char('i') => app.start_inline_edit(),
+            KeyCode::Delete => app.cancel_selected(),
+            KeyCode::Char('P') => app.terminal_request = Some(TerminalRequest::Page),
+            KeyCode::Char('?') => app.open_help(),
+            KeyCode::Enter => {
+                app.last_enter = Some(Instant::now());
+                if let Err(err) = app.execute_command() {
+                    app.status_message = Some(format!("failed to run command: {}", err));
+                }
+            }
+            code => {
+                if !app.handle_output_key(code) {
+                    return KeyOutcome::Ignored;
+                }
+            }
+        },
+        AppMode::Confirming => match key.code {
+            // a lone Enter after the question shows up does
+            // nothing, a quick double Enter runs right away
+            KeyCode::Char('y') | KeyCode::Enter
+                if key.code != KeyCode::Enter || app.is_double_enter() =>
+            {
+                if let Err(err) = app.confirm_command() {
+                    app.status_message = Some(format!("failed to run command: {}", err));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => app.cancel_confirm(),
+            _ => return KeyOutcome::Ignored,
+        },
+        AppMode::Searching => match key.code {
+            KeyCode::Esc => {
+                app.set_mode(AppMode::Normal);
+                app.search_input.clear();
+                app.update_filter();
+            }
+            KeyCode::Enter => {
+                if let Err(err) = app.submit_search() {
+                    app.status_message = Some(format!("failed to run command: {}", err));
+                }
+            }
+            // arrows keep navigating the live results while
+            // letters (including j/k) go into the query
+            KeyCode::Down => app.next(),
+            KeyCode::Up => app.previous(),
+            _ => match app.search_input.handle_key(key) {
+                KeyEffect::Changed => app.update_filter(),
+                KeyEffect::Unchanged => {}
+                KeyEffect::Ignored => return KeyOutcome::Ignored,
+            },
+        },
+        AppMode::FilteringOutput => match key.code {
+            KeyCode::Esc => {
+                app.set_mode(AppMode::Normal);
+                app.output_filter.clear();
+                app.output_filter_enabled = false;
+            }
+            KeyCode::Enter => app.set_mode(AppMode::Normal),
+            _ => {
+                if app.output_filter.handle_key(key) == KeyEffect::Ignored {
+                    return KeyOutcome::Ignored;
+                }
+            }
+        },
+        AppMode::Prompting => match key.code {
+            KeyCode::Esc => app.cancel_prompt(),
+            KeyCode::Enter => {
+                if let Err(err) = app.submit_prompt() {
+                    app.status_message = Some(format!("failed to run command: {}", err));
+                }
+            }
+            _ => {
+                let effect = app
+                    .prompt
+                    .as_mut()
+                    .map_or(KeyEffect::Ignored, |prompt| prompt.input.handle_key(key));
+                if effect == KeyEffect::Ignored {
+                    return KeyOutcome::Ignored;
+                }
+            }
+        },
+        AppMode::EditingCommand => match key.code {
+            KeyCode::Esc => app.cancel_inline_edit(),
+            KeyCode::Enter => {
+                if let Err(err) = app.submit_inline_edit() {
+                    app.status_message = Some(format!("failed to run command: {}", err));
+                }
+            }
+            _ => {
+                let effect = app
+                    .inline_edit
+                    .as_mut()
+                    .map_or(KeyEffect::Ignored, |edit| edit.input.handle_key(key));
+                if effect == KeyEffect::Ignored {
+                    return KeyOutcome::Ignored;
+                }
+            }
+        },
+        AppMode::Palette => match key.code {
+            KeyCode::Esc => app.close_palette(),
+            KeyCode::Enter => app.submit_palette(),
+            KeyCode::Down => app.move_palette(1),
+            KeyCode::Up => app.move_palette(-1),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.move_palette(1)
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.move_palette(-1)
+            }
+            _ => {
+                let effect = app
+                    .palette
+                    .as_mut()
+                    .map_or(KeyEffect::Ignored, |palette| palette.input.handle_key(key));
+                match effect {
+                    KeyEffect::Changed => app.update_palette(),
+                    KeyEffect::Unchanged => {}
+                    KeyEffect::Ignored => return KeyOutcome::Ignored,
+                }
+            }
+        },
+        AppMode::Help => match key.code {
+            KeyCode::Esc => app.close_help(),
+            KeyCode::Down => app.scroll_help(1),
+            KeyCode::Up => app.scroll_help(-1),
+            KeyCode::PageDown => app.scroll_help(HELP_PAGE),
+            KeyCode::PageUp => app.scroll_help(-HELP_PAGE),
+            _ => {
+                let Some(help) = app.help.as_mut() else {
+                    return KeyOutcome::Ignored;
+                };
+                match help.input.handle_key(key) {
+                    // a new search starts from the top
+                    KeyEffect::Changed => help.scroll = 0,
+                    KeyEffect::Unchanged => {}
+                    KeyEffect::Ignored => return KeyOutcome::Ignored,
+                }
+            }
+        },
+        AppMode::Banner => match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => app.dismiss_banner(),
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.dismiss_banner();
+                app.reload_config();
+            }
+            _ => return KeyOutcome::Ignored,
+        },
+        AppMode::History => match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('H') => app.set_mode(AppMode::Normal),
+            KeyCode::Char('j') | KeyCode::Down => {
+                app.history_selected = (app.history_selected + 1).min(app.history.len() - 1);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.history_selected = app.history_selected.saturating_sub(1);
+            }
+            KeyCode::Char('t') => app.history_absolute = !app.history_absolute,
+            KeyCode::Enter => app.restore_run(),
+            _ => return KeyOutcome::Ignored,
+        },
+        AppMode::Running => match key.code {
+            KeyCode::Esc => app.cancel_command(),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.signal_command(Signal::Interrupt)
+            }
+            KeyCode::Char('x') => app.terminate_command(),
+            KeyCode::Char('X') => app.signal_command(Signal::Kill),
+            KeyCode::Char('j') | KeyCode::Down => app.next(),
+            KeyCode::Char('k') | KeyCode::Up => app.previous(),
+            KeyCode::Delete => app.cancel_selected(),
+            KeyCode::Char('M') => app.terminal_request = Some(TerminalRequest::ToggleMouse),
+            KeyCode::Char('?') => app.open_help(),
+            code => {
+                if !app.handle_output_key(code) {
+                    return KeyOutcome::Ignored;
+                }
+            }
+        },
+    }
+    KeyOutcome::Handled
+}
+
+// hands the terminal to $EDITOR to write a one-off command, then runs it
+fn edit_command(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> io::Result<()> {
+    let initial = app
+        .selected_index
+        .map(|idx| app.commands[idx].command.clone())
+        .unwrap_or_default();
+    // the editor needs the real terminal while it runs
+    suspend_terminal(terminal)?;
+    let outcome = editor::edit(&initial);
+    resume_terminal(terminal, app.mouse_capture)?;
+    app.handle_edit_outcome(outcome);
+    Ok(())
+}
+
+// hands the terminal to $PAGER with the output in it, until it's closed
+fn page_output(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> io::Result<()> {
+    if app.command_output.is_empty() {
+        app.status_message = Some("no output to page".into());
+        return Ok(());
+    }
+    suspend_terminal(terminal)?;
+    let result = pager::page(&app.command_output);
+    resume_terminal(terminal, app.mouse_capture)?;
+    match result {
+        Ok(status) if !status.success() => {
+            app.status_message = Some(format!("pager exited with {}", status));
+        }
+        Ok(_) => {}
+        Err(err) => {
+            log!("pager failed: {}", err);
+            app.status_message = Some(format!("could not open the pager: {}", err));
+        }
+    }
+    Ok(())
+}
+
+// hands the terminal back to the shell (or a child like $EDITOR)
+fn suspend_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+    )?;
+    terminal.show_cursor()
+}
+
+fn resume_terminal(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mouse_capture: bool,
+) -> io::Result<()> {
+    enable_raw_mode()?;
+    // a paste arrives as one event instead of keys, so a newline in it
+    // can't act as Enter
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableBracketedPaste
+    )?;
+    if mouse_capture {
+        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+    }
+    // whatever ran in between may have scribbled over the screen
+    terminal.clear()
+}
+
+// with mouse capture on the terminal can't select text, so it can be
+// turned off (and back on) at any time
+fn toggle_mouse_capture(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> io::Result<()> {
+    app.mouse_capture = !app.mouse_capture;
+    if app.mouse_capture {
+        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+        app.status_message = Some("mouse capture on".into());
+    } else {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+        app.status_message = Some("mouse capture off, the terminal can select text again".into());
+    }
+    Ok(())
+}
+
+// one command per non-empty line, e.g. `cat mycommands.txt | command-runner-tui`
+fn read_piped_commands() -> io::Result<Vec<CommandEntry>> {
+    let mut commands = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            commands.push(CommandEntry::new(line));
+        }
+    }
+    Ok(commands)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::from_env().unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, cli::USAGE);
+        process::exit(2);
+    });
+
+    if let Some(path) = &cli.log {
+        logging::init(path)?;
+    }
+
+    // without the TUI a broken config can only be reported on stderr
+    let load_config = || {
+        Config::load_all(&cli.config).unwrap_or_else(|err| {
+            log!("failed to load config: {}", err);
+            eprintln!("{}", err);
+            process::exit(1);
+        })
+    };
+    if let Some(Subcommand::Run { name, json }) = &cli.subcommand {
+        process::exit(batch::run(&load_config(), name, *json));
+    }
+    if let Some(Subcommand::Export { path }) = &cli.subcommand {
+        process::exit(export::run(&load_config(), path.as_deref()));
+    }
+
+    // stdin has to be consumed before raw mode. crossterm falls back to
+    // /dev/tty for key events when stdin isn't a terminal, so the event loop
+    // keeps working after the pipe is drained
+    let mut source: Box<dyn CommandSource> = Box::new(FileCommandSource::new(cli.config.clone()));
+    let mut piped_commands = false;
+    if cli.stdin || !io::stdin().is_terminal() {
+        let piped = read_piped_commands()?;
+        if !piped.is_empty() {
+            // a config reload must not replace piped commands
+            source = Box::new(StaticCommandSource::new(piped));
+            piped_commands = true;
+        }
+    }
 
-    // debug
-    let debug_block = Block::default().title("debug").borders(Borders::ALL);
-    let debug_text = format!(
-        "Selected index: {}, filtered: {:?}, search_input: {}",
-        app.selected_index.unwrap_or(20),
-        app.filtered_commands,
-        app.search_input
+    // setup terminal
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    resume_terminal(&mut terminal, true)?;
+    terminal.draw(|frame| ui::loading(frame, "Loading config…"))?;
+
+    // a broken config doesn't keep the app from starting, the built-in
+    // commands are used and the error is shown until dismissed
+    let (config, config_error) = match Config::load_all(&cli.config) {
+        Ok(config) => (config, None),
+        Err(err) => {
+            log!("failed to load config: {}", err);
+            let config = Config {
+                path: cli.config.last().cloned().or_else(config::default_path),
+                ..Config::default()
+            };
+            (config, Some(err))
+        }
+    };
+    log!(
+        "loaded {} commands from {:?}",
+        config.commands.len(),
+        config.path
     );
-    frame.render_widget(Paragraph::new(debug_text).block(debug_block), layout[3]);
-    // end debug
+
+    // create app state
+    let mut app = App::new(config, source);
+    app.config_files = cli.config.clone();
+    if let Some(err) = config_error {
+        // the source reads the same file, the banner already says why
+        app.status_message = None;
+        // an error from one of several files already names it
+        let message = match err {
+            ConfigError::File { .. } => err.to_string(),
+            err => {
+                let path = app.config_path.clone().unwrap_or_default();
+                format!("{}: {}", path.display(), err)
+            }
+        };
+        app.show_banner(format!(
+            "{}\n\nUsing the built-in commands until it's fixed, ctrl+r reloads it.",
+            message
+        ));
+    }
+    // a piped list is a one-off, it shouldn't restore or overwrite the state
+    // of the configured one
+    if !piped_commands {
+        app.state_path = state::default_path();
+        app.load_state();
+    }
+
+    event_loop(
+        &mut terminal,
+        &mut app,
+        &mut TerminalEvents,
+        |terminal, app, request| match request {
+            TerminalRequest::Edit => edit_command(terminal, app),
+            TerminalRequest::Page => page_output(terminal, app),
+            TerminalRequest::ToggleMouse => toggle_mouse_capture(terminal, app),
+        },
+    )?;
+
+    suspend_terminal(&mut terminal)?;
+    if let Err(err) = app.save_state() {
+        eprintln!("could not save state: {}", err);
+    }
+    log!("exiting");
+
+    Ok(())
+}
+
+// draws and handles events until the app quits or `events` runs out.
+// `on_request` does what's left in `terminal_request`, the keys that need
+// the real terminal
+fn event_loop<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    events: &mut impl EventSource,
+    mut on_request: impl FnMut(&mut Terminal<B>, &mut App, TerminalRequest) -> io::Result<()>,
+) -> io::Result<()> {
+    loop {
+        if app.in_flight() {
+            app.tick();
+        }
+        app.check_filter();
+        app.check_notification();
+
+        terminal.draw(|frame| ui(frame, app))?;
+        if events.is_done() {
+            return Ok(());
+        }
+
+        // handle events
+        // wake up in time for the next spinner frame while something runs
+        let timeout = if app.in_flight() || app.is_filtering() {
+            app.poll_interval.min(app.spinner_interval)
+        } else {
+            app.poll_interval
+        };
+        let Some(event) = events.next_event(timeout)? else {
+            continue;
+        };
+        if run_once(app, event).is_break() {
+            return Ok(());
+        }
+        if let Some(request) = app.terminal_request.take() {
+            on_request(terminal, app, request)?;
+        }
+    }
+}
+
+// an app listing `commands`, without a config file or saved state
+#[cfg(test)]
+fn test_app(commands: &[&str]) -> App {
+    test_app_with(commands.iter().map(|c| CommandEntry::new(c)).collect())
+}
+
+#[cfg(test)]
+fn test_app_with(commands: Vec<CommandEntry>) -> App {
+    let config = Config {
+        commands: commands.clone(),
+        ..Config::default()
+    };
+    App::new(config, Box::new(StaticCommandSource::new(commands)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+    use ratatui::backend::TestBackend;
+    use std::{env, fs};
+
+    fn keys(codes: &[KeyCode]) -> VecDeque<Event> {
+        codes
+            .iter()
+            .map(|&code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+            .collect()
+    }
+
+    fn typed(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    // runs `events` to the end and returns what is on the screen
+    fn drive(app: &mut App, events: &mut VecDeque<Event>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        event_loop(&mut terminal, app, events, |_, _, _| Ok(())).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // the lines `spawn_reader` sends for everything `reader` yields
+    fn read_lines(reader: impl Read + Send + 'static, stream: Stream) -> Vec<String> {
+        let (tx, rx) = mpsc::sync_channel(16);
+        spawn_reader(reader, tx, stream).join().unwrap();
+        rx.try_iter()
+            .map(|message| match message {
+                OutputMessage::Line(line, _) => line,
+                OutputMessage::CommandDone { .. } => panic!("a reader doesn't finish the run"),
+            })
+            .collect()
+    }
+
+    // hands out its reads one at a time, then end of file
+    struct ScriptedReader(VecDeque<io::Result<&'static [u8]>>);
+
+    impl Read for ScriptedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.pop_front() {
+                None => Ok(0),
+                Some(Ok(bytes)) => {
+                    buf[..bytes.len()].copy_from_slice(bytes);
+                    Ok(bytes.len())
+                }
+                Some(Err(err)) => Err(err),
+            }
+        }
+    }
+
+    #[test]
+    fn a_last_line_without_newline_is_kept() {
+        assert_eq!(
+            read_lines(io::Cursor::new(b"one\ntwo".to_vec()), Stream::Stdout),
+            ["one", "two"]
+        );
+        assert!(read_lines(io::Cursor::new(Vec::new()), Stream::Stdout).is_empty());
+    }
+
+    #[test]
+    fn an_interrupted_read_keeps_the_partial_line() {
+        let reader = ScriptedReader(VecDeque::from([
+            Ok(&b"hal"[..]),
+            Err(io::ErrorKind::Interrupted.into()),
+            Ok(&b"f\nnext\n"[..]),
+        ]));
+        assert_eq!(read_lines(reader, Stream::Stdout), ["half", "next"]);
+    }
+
+    #[test]
+    fn a_failing_pipe_sends_what_was_read() {
+        let reader = ScriptedReader(VecDeque::from([
+            Ok(&b"done\nparti"[..]),
+            Err(io::Error::other("input/output error")),
+            Ok(&b"never read\n"[..]),
+        ]));
+        assert_eq!(read_lines(reader, Stream::Stdout), ["done", "parti"]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_not_dropped() {
+        let bytes = b"ok\n\xff\xfebad \xe2\x9c\r\nafter\n".to_vec();
+        assert_eq!(
+            read_lines(io::Cursor::new(bytes), Stream::Stdout),
+            ["ok", "\u{FFFD}\u{FFFD}bad \u{FFFD}", "after"]
+        );
+        assert_eq!(
+            read_lines(io::Cursor::new(b"\x80\n".to_vec()), Stream::Stderr),
+            ["Error: \u{FFFD}"]
+        );
+    }
+
+    // reloads with `commands` as the new list, the config itself is empty
+    fn reload_with(app: &mut App, commands: &[&str]) {
+        let path = env::temp_dir().join(format!(
+            "command-runner-test-{}-{:?}.toml",
+            process::id(),
+            thread::current().id()
+        ));
+        fs::write(&path, "").unwrap();
+        app.config_files = vec![path.clone()];
+        app.source = Box::new(StaticCommandSource::new(
+            commands.iter().map(|c| CommandEntry::new(c)).collect(),
+        ));
+        app.reload_config();
+        let _ = fs::remove_file(path);
+    }
+
+    fn selected(app: &App) -> Option<&str> {
+        app.selected_index
+            .map(|idx| app.commands[idx].command.as_str())
+    }
+
+    fn search(app: &mut App, query: &str) {
+        app.search_input.set(query);
+        app.filter_now();
+    }
+
+    #[test]
+    fn reload_keeps_the_selected_command_when_entries_are_added() {
+        let mut app = test_app(&["cargo build", "cargo test", "ls"]);
+        search(&mut app, "cargo");
+        app.next();
+        assert_eq!(selected(&app), Some("cargo test"));
+
+        reload_with(&mut app, &["cargo fmt", "cargo build", "ls", "cargo test"]);
+        assert_eq!(selected(&app), Some("cargo test"));
+        assert_eq!(app.filtered_commands.len(), 3);
+        assert!(app.filtered_commands.contains(&3));
+    }
+
+    #[test]
+    fn reload_moves_the_selection_off_a_removed_command() {
+        let mut app = test_app(&["cargo build", "cargo test", "ls"]);
+        search(&mut app, "cargo");
+        app.next();
+        reload_with(&mut app, &["ls", "cargo build"]);
+        assert_eq!(app.filtered_commands, [1]);
+        assert_eq!(selected(&app), Some("cargo build"));
+
+        reload_with(&mut app, &["ls"]);
+        assert!(app.filtered_commands.is_empty());
+        assert_eq!(app.selected_index, None);
+        app.next();
+        app.previous();
+        assert_eq!(app.selected_index, None);
+    }
+
+    #[test]
+    fn reload_of_a_big_list_never_shows_stale_indices() {
+        let many: Vec<String> = (0..matcher::WORKER_THRESHOLD * 2)
+            .map(|i| format!("echo {}", i))
+            .collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        let mut app = test_app(&many);
+        search(&mut app, "echo");
+        app.selected_index = app.filtered_commands.last().copied();
+
+        // still big enough for a worker, which has no result yet
+        reload_with(&mut app, &many[..matcher::WORKER_THRESHOLD]);
+        assert_eq!(app.filtered_commands.len(), matcher::WORKER_THRESHOLD);
+        assert!(!app.is_filtering());
+        assert_eq!(selected(&app), Some("echo 0"));
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| ui(frame, &mut app)).unwrap();
+    }
+
+    fn press(app: &mut App, codes: &[KeyCode]) {
+        for event in keys(codes) {
+            let _ = run_once(app, event);
+        }
+    }
+
+    #[test]
+    fn an_edited_command_still_asks_first() {
+        let mut app = test_app_with(vec![CommandEntry {
+            confirm: true,
+            ..CommandEntry::new("rm -rf build")
+        }]);
+        press(&mut app, &[KeyCode::Char('i'), KeyCode::End]);
+        press(&mut app, &typed("/tmp"));
+        press(&mut app, &[KeyCode::Enter]);
+        assert_eq!(app.mode, AppMode::Confirming);
+        let edited = app.confirming.as_ref().unwrap().entry(&app.commands);
+        assert_eq!(edited.command, "rm -rf build/tmp");
+        assert!(drive(&mut app, &mut VecDeque::new()).contains("Run `rm -rf build/tmp`?"));
+
+        press(&mut app, &[KeyCode::Char('n')]);
+        assert_eq!(app.mode, AppMode::Normal);
+        assert!(app.confirming.is_none());
+        assert!(app.running_command.is_none());
+    }
+
+    #[test]
+    fn an_edited_command_for_another_os_is_refused() {
+        let mut app = test_app_with(vec![CommandEntry {
+            os: vec!["plan9".into()],
+            ..CommandEntry::new("ls")
+        }]);
+        press(&mut app, &[KeyCode::Char('i'), KeyCode::Enter]);
+        assert_eq!(app.mode, AppMode::Normal);
+        assert!(app.running_command.is_none());
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("`ls` is only available on plan9")
+        );
+    }
+
+    #[test]
+    fn a_command_that_cant_start_isnt_counted_as_run() {
+        let mut app = test_app_with(vec![CommandEntry {
+            depends_on: vec!["missing".into()],
+            ..CommandEntry::new("make deploy")
+        }]);
+        press(&mut app, &[KeyCode::Enter]);
+        assert!(app.running_command.is_none());
+        assert!(app.running_index.is_none());
+        assert!(app.run_stats(0).is_none());
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("can't run `make deploy`: `make deploy` depends on `missing`, which isn't a command")
+        );
+    }
+
+    #[test]
+    fn copied_commands_keep_variables_unexpanded() {
+        env::set_var("COMMAND_RUNNER_TEST_SECRET", "hunter2");
+        let app = test_app(&["curl -H \"token: $COMMAND_RUNNER_TEST_SECRET\" {url}"]);
+        let command = app.resolved_command().unwrap();
+        assert!(command.contains("$COMMAND_RUNNER_TEST_SECRET"));
+        assert!(!command.contains("hunter2"));
+    }
+
+    #[test]
+    fn search_narrows_the_list_and_esc_clears_it() {
+        let mut app = test_app(&["cargo build", "cargo test", "ls -la"]);
+        let mut codes = vec![KeyCode::Char('/')];
+        codes.extend(typed("test"));
+        let screen = drive(&mut app, &mut keys(&codes));
+        assert_eq!(app.mode, AppMode::Searching);
+        assert!(screen.contains("SEARCH"));
+        assert!(screen.contains("cargo test"));
+        assert!(!screen.contains("ls -la"));
+        assert_eq!(app.selected_index, Some(1));
+
+        let screen = drive(&mut app, &mut keys(&[KeyCode::Esc]));
+        assert_eq!(app.mode, AppMode::Normal);
+        assert!(screen.contains("NORMAL"));
+        assert!(screen.contains("ls -la"));
+        assert_eq!(app.search_input.as_str(), "");
+    }
+
+    #[test]
+    fn keys_move_the_selection() {
+        let mut app = test_app(&["one", "two", "three"]);
+        drive(
+            &mut app,
+            &mut keys(&[KeyCode::Char('j'), KeyCode::Char('j'), KeyCode::Char('j')]),
+        );
+        assert_eq!(app.selected_index, Some(2));
+        drive(&mut app, &mut keys(&[KeyCode::Up]));
+        assert_eq!(app.selected_index, Some(1));
+    }
+
+    #[test]
+    fn terminal_keys_are_handed_to_the_caller() {
+        let mut app = test_app(&["one"]);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut requests = Vec::new();
+        event_loop(
+            &mut terminal,
+            &mut app,
+            &mut keys(&[KeyCode::Char('M'), KeyCode::Char('P')]),
+            |_, _, request| {
+                requests.push(request);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            requests,
+            [TerminalRequest::ToggleMouse, TerminalRequest::Page]
+        );
+    }
+
+    #[test]
+    fn the_loop_stops_on_quit() {
+        let mut app = test_app(&["one"]);
+        let mut events = keys(&[KeyCode::Char('q'), KeyCode::Char('j')]);
+        drive(&mut app, &mut events);
+        assert_eq!(events.len(), 1);
+    }
 }
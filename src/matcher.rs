@@ -0,0 +1,161 @@
+//! Matching the command list against the search query. Big lists are
+//! matched on a worker thread, so typing doesn't stutter while every entry
+//! is checked against the new query.
+
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use crate::{config::CommandEntry, search_terms};
+
+/// From this many commands on the list is matched by a [`Worker`].
+pub const WORKER_THRESHOLD: usize = 5_000;
+
+/// The entries matching `query`, in list order: those that have every
+/// `tag:` of the query and a searchable field (command, description,
+/// aliases) containing the rest, ignoring case.
+pub fn matching(commands: &[CommandEntry], query: &str) -> Vec<usize> {
+    let (tags, text) = search_terms(query);
+    let text = text.to_lowercase();
+    commands
+        .iter()
+        .enumerate()
+        .filter(|(_, cmd)| {
+            tags.iter().all(|tag| cmd.has_tag(tag))
+                && cmd
+                    .searchable_fields()
+                    .any(|field| field.to_lowercase().contains(&text))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// A thread matching queries against a snapshot of the list. Only the
+/// newest query counts: older ones still waiting are skipped and results
+/// for them are thrown away. The thread ends when the worker is dropped.
+#[derive(Debug)]
+pub struct Worker {
+    queries: Sender<(u64, String)>,
+    results: Receiver<(u64, Vec<usize>)>,
+    // of the last query sent
+    generation: u64,
+    pending: bool,
+}
+
+impl Worker {
+    pub fn spawn(commands: Arc<[CommandEntry]>) -> Worker {
+        let (queries, query_rx) = mpsc::channel::<(u64, String)>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(mut query) = query_rx.recv() {
+                // catch up with the typing before doing any work
+                loop {
+                    match query_rx.try_recv() {
+                        Ok(newer) => query = newer,
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+                let (generation, query) = query;
+                if result_tx
+                    .send((generation, matching(&commands, &query)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+        Worker {
+            queries,
+            results,
+            generation: 0,
+            pending: false,
+        }
+    }
+
+    pub fn request(&mut self, query: &str) {
+        self.generation += 1;
+        self.pending = self
+            .queries
+            .send((self.generation, query.to_string()))
+            .is_ok();
+    }
+
+    /// Drops the result of the last query, for a caller that matched the
+    /// list itself.
+    pub fn cancel(&mut self) {
+        self.generation += 1;
+        self.pending = false;
+    }
+
+    /// Whether a query was sent that has no result yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// The matches for the newest query, once they're in.
+    pub fn poll(&mut self) -> Option<Vec<usize>> {
+        let mut latest = None;
+        while let Ok((generation, matches)) = self.results.try_recv() {
+            if generation == self.generation {
+                latest = Some(matches);
+            }
+        }
+        if latest.is_some() {
+            self.pending = false;
+        }
+        latest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn worker(commands: &[&str]) -> Worker {
+        Worker::spawn(commands.iter().map(|c| CommandEntry::new(c)).collect())
+    }
+
+    // polls until a result comes in or a second has passed
+    fn wait(worker: &mut Worker) -> Option<Vec<usize>> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Some(matches) = worker.poll() {
+                return Some(matches);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        None
+    }
+
+    #[test]
+    fn only_the_newest_query_is_answered() {
+        let mut worker = worker(&["cargo build", "cargo test", "ls"]);
+        worker.request("cargo");
+        worker.request("test");
+        assert!(worker.is_pending());
+        assert_eq!(wait(&mut worker), Some(vec![1]));
+        assert!(!worker.is_pending());
+        // the result for "cargo", if it was matched at all, is dropped
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(worker.poll(), None);
+    }
+
+    #[test]
+    fn a_cancelled_query_is_never_answered() {
+        let mut worker = worker(&["cargo build", "ls"]);
+        worker.request("ls");
+        worker.cancel();
+        assert!(!worker.is_pending());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(worker.poll(), None);
+
+        worker.request("cargo");
+        assert_eq!(wait(&mut worker), Some(vec![0]));
+    }
+}
@@ -0,0 +1,147 @@
+//! Telling the user a run finished, for commands that take long enough to
+//! switch away from the terminal.
+//!
+//! The bell always works. Desktop notifications need the `notify` feature
+//! and, like the clipboard, go through the platform's tool (`notify-send`
+//! or `osascript`).
+
+use std::{
+    io::{self, Write},
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+/// `notify_on_complete`: how a finished run is announced.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notify {
+    #[default]
+    Off,
+    /// a terminal bell, which most terminals turn into an urgent hint
+    Bell,
+    Desktop,
+    /// bell and desktop notification
+    Both,
+}
+
+impl Notify {
+    pub const NAMES: &'static [&'static str] = &["off", "bell", "desktop", "both"];
+
+    pub fn from_name(name: &str) -> Option<Notify> {
+        match name {
+            "off" => Some(Notify::Off),
+            "bell" => Some(Notify::Bell),
+            "desktop" => Some(Notify::Desktop),
+            "both" => Some(Notify::Both),
+            _ => None,
+        }
+    }
+
+    fn bell(self) -> bool {
+        matches!(self, Notify::Bell | Notify::Both)
+    }
+
+    fn desktop(self) -> bool {
+        matches!(self, Notify::Desktop | Notify::Both)
+    }
+}
+
+/// A desktop notification whose tool is still running.
+#[derive(Debug)]
+pub struct Delivery(Receiver<io::Result<()>>);
+
+impl Delivery {
+    /// `None` until the tool is done, then whether it showed the
+    /// notification.
+    pub fn poll(&self) -> Option<io::Result<()>> {
+        match self.0.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Ok(())),
+        }
+    }
+}
+
+/// Announces that `command` finished with `summary` (e.g. `exit code 1`).
+/// A desktop notification is only handed to the platform's tool, the
+/// returned [`Delivery`] tells whether it worked.
+pub fn send(notify: Notify, command: &str, summary: &str) -> io::Result<Option<Delivery>> {
+    if notify.bell() {
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x07")?;
+        stdout.flush()?;
+    }
+    if notify.desktop() {
+        return desktop(&format!("{} finished", command), summary).map(Some);
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "notify")]
+fn desktop(title: &str, body: &str) -> io::Result<Delivery> {
+    use std::{
+        process::{Command, Stdio},
+        sync::mpsc,
+        thread,
+    };
+
+    let mut command = if cfg!(target_os = "macos") {
+        // AppleScript strings are double-quoted with backslash escapes
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            &format!(
+                "display notification \"{}\" with title \"{}\"",
+                quote(body),
+                quote(title)
+            ),
+        ]);
+        command
+    } else if cfg!(windows) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "desktop notifications aren't supported on Windows",
+        ));
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=command-runner", title, body]);
+        command
+    };
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(err.kind(), format!("`{}` not found", program))
+            }
+            _ => err,
+        })?;
+    // reaped in the background, the UI doesn't wait for the notification.
+    // `notify-send` without a notification daemon fails only now
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = child.wait_with_output().and_then(|output| {
+            if output.status.success() {
+                return Ok(());
+            }
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(io::Error::other(match stderr.trim() {
+                "" => format!("`{}` {}", program, output.status),
+                stderr => format!("`{}` {}: {}", program, output.status, stderr),
+            }))
+        });
+        let _ = tx.send(result);
+    });
+    Ok(Delivery(rx))
+}
+
+#[cfg(not(feature = "notify"))]
+fn desktop(_title: &str, _body: &str) -> io::Result<Delivery> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "desktop notification support is not compiled in",
+    ))
+}
@@ -0,0 +1,44 @@
+//! Showing the output in `$PAGER`, for output too big to read comfortably
+//! in the Output pane.
+//!
+//! Like [`editor`](crate::editor), the caller hands the terminal over before
+//! calling [`page`] and takes it back afterwards.
+
+use std::{
+    env, fs, io,
+    process::{Command, ExitStatus},
+};
+
+use crate::editor;
+
+fn pager_command() -> String {
+    env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "more" } else { "less" }.to_string())
+}
+
+/// Writes `text` to a temp file and opens the pager on it, returning the
+/// pager's exit status once it's closed. The file is removed either way.
+pub fn page(text: &str) -> io::Result<ExitStatus> {
+    let path = editor::temp_file_path("output");
+    fs::write(&path, text)?;
+
+    // $PAGER may carry flags, e.g. `less -R`
+    let pager = pager_command();
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().unwrap_or("less");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                err.kind(),
+                format!("pager `{}` not found, set $PAGER", program),
+            ),
+            _ => err,
+        });
+    let _ = fs::remove_file(&path);
+    status
+}
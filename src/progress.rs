@@ -0,0 +1,120 @@
+//! Finding how far along a command is in its output, for commands with a
+//! `progress` pattern.
+//!
+//! A pattern is plain text with two wildcards: `{}` stands for the
+//! percentage, `*` for any text, and everything else matches itself. A
+//! pattern can match anywhere in a line, so `"{}%"` finds `45%` in
+//! `downloading [====>   ] 45% 1.2MB/s`.
+
+/// Whether `pattern` can be used, i.e. has exactly one `{}`.
+pub fn is_valid(pattern: &str) -> bool {
+    pattern.matches("{}").count() == 1
+}
+
+/// The percentage `pattern` finds in `line`, clamped to `0..=100`. A line
+/// redrawn in place with `\r` counts by its last state.
+pub fn parse(pattern: &str, line: &str) -> Option<f64> {
+    let tokens = tokenize(pattern);
+    line.rsplit('\r').find_map(|part| {
+        part.char_indices()
+            .find_map(|(start, _)| match_at(&tokens, &part[start..]))
+            .map(|percent| percent.clamp(0.0, 100.0))
+    })
+}
+
+#[derive(Debug)]
+enum Token<'a> {
+    Literal(&'a str),
+    Any,
+    Number,
+}
+
+fn tokenize(pattern: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = pattern;
+    while let Some(at) = rest.find(['*', '{']) {
+        if at > 0 {
+            tokens.push(Token::Literal(&rest[..at]));
+        }
+        rest = if rest[at..].starts_with('*') {
+            tokens.push(Token::Any);
+            &rest[at + 1..]
+        } else if rest[at..].starts_with("{}") {
+            tokens.push(Token::Number);
+            &rest[at + 2..]
+        } else {
+            tokens.push(Token::Literal("{"));
+            &rest[at + 1..]
+        };
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Literal(rest));
+    }
+    tokens
+}
+
+// the number the tokens capture when they match the start of `text`
+fn match_at(tokens: &[Token], text: &str) -> Option<f64> {
+    let Some((token, rest)) = tokens.split_first() else {
+        // matched, the value comes from the `{}` on the way back
+        return Some(f64::NAN);
+    };
+    match token {
+        Token::Literal(literal) => match_at(rest, text.strip_prefix(literal)?),
+        // as little as possible, so `*{}%` finds the first percentage
+        Token::Any => text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .find_map(|i| match_at(rest, &text[i..])),
+        Token::Number => {
+            let len = text
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(text.len());
+            let value: f64 = text[..len].parse().ok()?;
+            match_at(rest, &text[len..]).map(|_| value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_percentage_anywhere_in_a_line() {
+        assert_eq!(
+            parse("{}%", "downloading [====>   ] 45.5% 1.2MB/s"),
+            Some(45.5)
+        );
+        assert_eq!(parse("step {}/100", "build: step 7/100 done"), Some(7.0));
+    }
+
+    #[test]
+    fn a_wildcard_takes_the_first_percentage() {
+        assert_eq!(parse("*{}%", "10% of 3 files, 20% overall"), Some(10.0));
+        assert_eq!(
+            parse("files*{}%", "10% of 3 files, 20% overall"),
+            Some(20.0)
+        );
+    }
+
+    #[test]
+    fn the_last_redraw_counts() {
+        assert_eq!(parse("{}%", "10%\r20%\r30% eta 1s"), Some(30.0));
+        // a state without a percentage falls back to the one before it
+        assert_eq!(parse("{}%", "10%\r50%\rfinishing"), Some(50.0));
+    }
+
+    #[test]
+    fn values_are_clamped() {
+        assert_eq!(parse("{}%", "150%"), Some(100.0));
+    }
+
+    #[test]
+    fn no_match_is_none() {
+        assert_eq!(parse("{}%", "no progress here"), None);
+        assert_eq!(parse("{}%", "%"), None);
+        assert_eq!(parse("[{}]", "[12"), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! Running a command under a pseudo-terminal, for programs that change
+//! behaviour (paging, colors, refusing to start) when stdout is a pipe.
+//!
+//! Only available on unix with the `pty` feature. It's built on `openpty`
+//! from libc, there is no ConPTY support for Windows, so there (and in builds
+//! without the feature) commands marked `pty = true` run over plain pipes,
+//! the way every other command does, with a status message saying so.
+
+use std::{fs::File, io, process::Child, process::Command};
+
+// programs that are known to want a terminal, used to warn when they are
+// run through plain pipes
+const TTY_PROGRAMS: &[&str] = &[
+    "top", "htop", "btop", "less", "more", "man", "vi", "vim", "nvim", "nano", "watch", "ssh",
+];
+
+/// Whether [`spawn`] can run commands, see [`unavailable_reason`] when not.
+pub fn available() -> bool {
+    cfg!(all(unix, feature = "pty"))
+}
+
+/// Why commands marked `pty = true` run over pipes in this build.
+pub fn unavailable_reason() -> &'static str {
+    if cfg!(unix) {
+        "pty support not compiled in (build with --features pty), using pipes"
+    } else {
+        "pty mode is only supported on unix, using pipes"
+    }
+}
+
+/// Whether `command` looks like it needs a terminal to work properly.
+pub fn wants_tty(command: &str) -> bool {
+    command
+        .split_whitespace()
+        .next()
+        .and_then(|program| program.rsplit(['/', '\\']).next())
+        .is_some_and(|program| TTY_PROGRAMS.contains(&program))
+}
+
+/// Spawns `command` with stdin/stdout/stderr attached to a new pty. Returns
+/// the child and the master side of the pty, where all of its output can be
+/// read (stdout and stderr are merged by the terminal).
+#[cfg(all(unix, feature = "pty"))]
+pub fn spawn(mut command: Command, size: (u16, u16)) -> io::Result<(Child, File)> {
+    use std::os::unix::{io::FromRawFd, process::CommandExt};
+    use std::process::Stdio;
+
+    let mut master = 0;
+    let mut slave = 0;
+    let winsize = libc::winsize {
+        ws_col: size.0.max(1),
+        ws_row: size.1.max(1),
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+
+    // SAFETY: plain FFI call, the out pointers are valid for the call
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &winsize,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: openpty succeeded, so both are open fds we now own
+    let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+
+    command
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+
+    // SAFETY: only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            // new session with the pty as controlling terminal, so the child
+            // gets job control and SIGWINCH like in a real terminal
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let child = command.spawn()?;
+    // the Command holds our copies of the slave fds, they have to be closed
+    // for reads on the master to end once the child exits
+    drop(command);
+    Ok((child, master))
+}
+
+#[cfg(not(all(unix, feature = "pty")))]
+pub fn spawn(_command: Command, _size: (u16, u16)) -> io::Result<(Child, File)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "pty support is not compiled in",
+    ))
+}
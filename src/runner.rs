@@ -0,0 +1,94 @@
+//! Turning a command string into a process, shared by the TUI and the
+//! batch `run` subcommand.
+
+use std::{
+    io::{self, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::logging::log;
+
+/// How long a failed command waits before it is run again (`retries`).
+pub const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+pub fn build(command: &str) -> Command {
+    // handle command creation based on the OS
+    if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut parts = command.split_whitespace();
+        let mut process = Command::new(parts.next().unwrap_or(""));
+        process.args(parts);
+        process
+    }
+}
+
+/// Spawns `command`, writing `stdin` to it when given (see [`feed_stdin`]).
+/// Without a payload its stdin is left as configured.
+pub fn spawn_with_stdin(command: &mut Command, stdin: Option<&str>) -> io::Result<Child> {
+    if stdin.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command.spawn()?;
+    if let Some(payload) = stdin {
+        feed_stdin(&mut child, payload.to_string());
+    }
+    Ok(child)
+}
+
+/// Writes `payload` to the child's piped stdin and closes it, so the
+/// command gets EOF. It's written from a thread of its own: a payload
+/// bigger than the pipe buffer would otherwise block until the command
+/// reads it. A command that exits without reading it all only breaks the
+/// pipe, which is logged and otherwise ignored.
+pub fn feed_stdin(child: &mut Child, payload: String) {
+    let Some(mut stdin) = child.stdin.take() else {
+        return;
+    };
+    let pid = child.id();
+    thread::spawn(move || match stdin.write_all(payload.as_bytes()) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+            log!("pid {} closed stdin before reading all of it", pid)
+        }
+        Err(err) => log!("failed to write stdin of pid {}: {}", pid, err),
+        // `stdin` is dropped, and so closed, either way
+    });
+}
+
+/// What [`build`] runs, written the way it would be typed into a shell.
+pub fn invocation(command: &str) -> String {
+    if cfg!(windows) {
+        format!("cmd /C {}", command)
+    } else {
+        command.to_string()
+    }
+}
+
+/// Strips the line terminator and lossily decodes, so output that isn't
+/// valid UTF-8 shows up with replacement characters instead of vanishing.
+pub fn decode_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// The exit code a shell would report, `128 + signal` for a killed process
+/// on unix.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    1
+}
@@ -0,0 +1,50 @@
+//! Signalling a running child beyond `Child::kill`.
+//!
+//! On unix this sends real signals so a command can shut down cleanly;
+//! Windows has no equivalent, so every signal there degrades to `kill()`.
+
+use std::{io, process::Child};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// SIGINT, what Ctrl+C would send in a shell
+    Interrupt,
+    /// SIGTERM, ask the process to exit
+    Terminate,
+    /// SIGKILL, can't be caught
+    Kill,
+}
+
+impl Signal {
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Interrupt => "SIGINT",
+            Signal::Terminate => "SIGTERM",
+            Signal::Kill => "SIGKILL",
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn send(child: &mut Child, signal: Signal) -> io::Result<()> {
+    let signal = match signal {
+        Signal::Interrupt => libc::SIGINT,
+        Signal::Terminate => libc::SIGTERM,
+        Signal::Kill => libc::SIGKILL,
+    };
+    // an exited but unreaped child still owns its pid, so this can't hit an
+    // unrelated process as long as we haven't waited on it yet
+    if child.try_wait()?.is_some() {
+        return Ok(());
+    }
+    // SAFETY: plain FFI call with a pid we own
+    if unsafe { libc::kill(child.id() as libc::pid_t, signal) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn send(child: &mut Child, _signal: Signal) -> io::Result<()> {
+    child.kill()
+}
@@ -0,0 +1,56 @@
+//! Where the command list comes from. The app asks its source again on every
+//! config reload, so a source can be anything that can produce the list on
+//! demand: the config files, or a fixed list like the commands piped in on
+//! stdin (which the tests use too).
+
+use std::{fmt, io, path::PathBuf};
+
+use crate::config::{CommandEntry, Config, ConfigError};
+
+pub trait CommandSource: fmt::Debug {
+    fn commands(&self) -> Result<Vec<CommandEntry>, ConfigError>;
+}
+
+/// A fixed list, e.g. commands piped in on stdin.
+#[derive(Debug, Clone)]
+pub struct StaticCommandSource {
+    commands: Vec<CommandEntry>,
+}
+
+impl StaticCommandSource {
+    pub fn new(commands: Vec<CommandEntry>) -> StaticCommandSource {
+        StaticCommandSource { commands }
+    }
+}
+
+impl CommandSource for StaticCommandSource {
+    fn commands(&self) -> Result<Vec<CommandEntry>, ConfigError> {
+        Ok(self.commands.clone())
+    }
+}
+
+/// The `[[commands]]` of the config files, merged, see [`Config::load_all`].
+/// A single file that doesn't exist (anymore) gives the built-in commands.
+#[derive(Debug, Clone)]
+pub struct FileCommandSource {
+    paths: Vec<PathBuf>,
+}
+
+impl FileCommandSource {
+    /// No paths reads the config from the default location.
+    pub fn new(paths: Vec<PathBuf>) -> FileCommandSource {
+        FileCommandSource { paths }
+    }
+}
+
+impl CommandSource for FileCommandSource {
+    fn commands(&self) -> Result<Vec<CommandEntry>, ConfigError> {
+        match Config::load_all(&self.paths) {
+            Ok(config) => Ok(config.commands),
+            Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                Ok(Config::default().commands)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
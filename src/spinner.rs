@@ -0,0 +1,30 @@
+//! Spinner frames shown next to a running command.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SpinnerStyle {
+    #[default]
+    Braille,
+    Dots,
+    Line,
+}
+
+impl SpinnerStyle {
+    pub const NAMES: &'static [&'static str] = &["braille", "dots", "line"];
+
+    pub fn from_name(name: &str) -> Option<SpinnerStyle> {
+        match name {
+            "braille" => Some(SpinnerStyle::Braille),
+            "dots" => Some(SpinnerStyle::Dots),
+            "line" => Some(SpinnerStyle::Line),
+            _ => None,
+        }
+    }
+
+    pub fn frames(self) -> &'static [&'static str] {
+        match self {
+            SpinnerStyle::Braille => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            SpinnerStyle::Dots => &["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"],
+            SpinnerStyle::Line => &["|", "/", "-", "\\"],
+        }
+    }
+}
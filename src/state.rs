@@ -0,0 +1,121 @@
+//! UI state remembered between sessions: the selected command and the search
+//! query. Kept apart from the config so the config file isn't rewritten on
+//! every exit.
+
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::config::{self, ConfigError, Value};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct State {
+    /// the selected command, by command string so edits to the config
+    /// that reorder the list don't select the wrong entry
+    pub selected: Option<String>,
+    pub search: String,
+    /// last value entered for each `{placeholder}`, offered again next time
+    pub placeholders: BTreeMap<String, String>,
+}
+
+/// `$XDG_STATE_HOME/command-runner/state.toml` (or the `%LOCALAPPDATA%`
+/// equivalent on Windows).
+pub fn default_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+    };
+
+    dir.map(|dir| dir.join("command-runner").join("state.toml"))
+}
+
+/// Reads the state file, a missing file is an empty state.
+pub fn load(path: &Path) -> Result<State, ConfigError> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(State::default()),
+        Err(err) => return Err(err.into()),
+    };
+    let document = config::parse_document(&source)?;
+
+    let mut state = State::default();
+    // a state file with unexpected values is ignored rather than an error,
+    // it's only ever written by us
+    if let Some(Value::String(selected)) = document.root.get("selected") {
+        state.selected = Some(selected.clone());
+    }
+    if let Some(Value::String(search)) = document.root.get("search") {
+        state.search = search.clone();
+    }
+    if let Some(values) = document.tables.get("placeholders") {
+        for (name, value) in values {
+            if let Value::String(value) = value {
+                state.placeholders.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    Ok(state)
+}
+
+pub fn save(path: &Path, state: &State) -> io::Result<()> {
+    let mut contents = String::new();
+    if let Some(selected) = &state.selected {
+        contents.push_str(&format!("selected = {}\n", Value::String(selected.clone())));
+    }
+    contents.push_str(&format!(
+        "search = {}\n",
+        Value::String(state.search.clone())
+    ));
+    if !state.placeholders.is_empty() {
+        contents.push_str("\n[placeholders]\n");
+        // placeholder names can be anything between the braces, quoting
+        // them keeps the ones that aren't bare keys parseable
+        for (name, value) in &state.placeholders {
+            contents.push_str(&format!(
+                "{} = {}\n",
+                Value::String(name.clone()),
+                Value::String(value.clone())
+            ));
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_state_loads_back() {
+        let path = env::temp_dir().join(format!(
+            "command-runner-test-{}-state.toml",
+            std::process::id()
+        ));
+        let state = State {
+            selected: Some("grep \"x\" src".into()),
+            search: "tag:net".into(),
+            placeholders: [
+                ("branch", "main"),
+                ("härte grad", "3"),
+                ("a.b = \"c\"", "\\d"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        };
+
+        save(&path, &state).unwrap();
+        let loaded = load(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), state);
+    }
+}
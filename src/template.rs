@@ -0,0 +1,98 @@
+//! `{name}` placeholders in commands, filled in from a prompt right before
+//! the command runs.
+//!
+//! Only braces around a plain name (letters, digits, `_` and `-`) count, so
+//! things like `awk '{print $1}'` are left alone, and neither is `${NAME}`,
+//! which is an environment variable (see [`expand`](crate::expand)).
+
+use std::collections::HashMap;
+
+// byte ranges of the placeholders in `text`, braces included
+fn find(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || loop {
+        let start = offset + text[offset..].find('{')?;
+        let len = text[start + 1..].find('}')?;
+        let name = &text[start + 1..start + 1 + len];
+        offset = start + 1;
+        let variable = text[..start].ends_with('$');
+        if !variable
+            && !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            offset = start + len + 2;
+            return Some((start, offset));
+        }
+    })
+}
+
+/// The distinct placeholder names in `text`, in order of first use.
+pub fn placeholders(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (start, end) in find(text) {
+        let name = &text[start + 1..end - 1];
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Replaces every placeholder that has a value, others are kept as typed.
+pub fn fill(text: &str, values: &HashMap<String, String>) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in find(text) {
+        if let Some(value) = values.get(&text[start + 1..end - 1]) {
+            filled.push_str(&text[copied..start]);
+            filled.push_str(value);
+            copied = end;
+        }
+    }
+    filled.push_str(&text[copied..]);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn names_are_listed_once_in_order_of_first_use() {
+        assert_eq!(
+            placeholders("deploy {env} {service} --tag {env}-{tag}"),
+            ["env", "service", "tag"]
+        );
+        assert!(placeholders("ls -la").is_empty());
+    }
+
+    #[test]
+    fn other_braces_are_left_alone() {
+        assert!(placeholders("awk '{print $1}' {} ${HOME} {a b}").is_empty());
+        assert!(placeholders("echo {name").is_empty());
+        // an unclosed `{` doesn't swallow the placeholder after it
+        assert_eq!(placeholders("echo {oops {name}"), ["name"]);
+        assert_eq!(placeholders("x}{y}"), ["y"]);
+    }
+
+    #[test]
+    fn fill_replaces_every_use() {
+        assert_eq!(
+            fill(
+                "ssh {host} 'awk \"{print $1}\"' && echo {host} {port} ${HOME}",
+                &values(&[("host", "web-1")])
+            ),
+            "ssh web-1 'awk \"{print $1}\"' && echo web-1 {port} ${HOME}"
+        );
+        assert_eq!(fill("echo {a{b}}", &values(&[("b", "x")])), "echo {ax}");
+    }
+}
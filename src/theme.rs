@@ -0,0 +1,126 @@
+//! The colors the UI draws with, picked from a few built-in presets with
+//! `theme = "..."` in the config or cycled with 'T'.
+
+use ratatui::style::Color;
+
+/// The colors of a preset, by what they're used for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// the selection and the normal mode badge
+    pub accent: Color,
+    /// hints, descriptions and other secondary text
+    pub muted: Color,
+    pub success: Color,
+    pub error: Color,
+    /// status messages, favorites and search matches
+    pub warning: Color,
+    /// aliases and the prompt badges
+    pub info: Color,
+    /// the history and palette badges
+    pub special: Color,
+    /// text on the mode badges and on the tags the search asks for
+    pub on_badge: Color,
+    /// the text of tag chips
+    pub chip: Color,
+    /// behind tag chips and the empty part of progress gauges
+    pub chip_background: Color,
+}
+
+/// `theme`: one of the built-in color sets.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Preset {
+    #[default]
+    Default,
+    /// brighter colors for dark terminal backgrounds
+    Dark,
+    /// darker colors that stay readable on a light background
+    Light,
+    HighContrast,
+}
+
+impl Preset {
+    pub const NAMES: &'static [&'static str] = &["default", "dark", "light", "high-contrast"];
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        match name {
+            "default" => Some(Preset::Default),
+            "dark" => Some(Preset::Dark),
+            "light" => Some(Preset::Light),
+            "high-contrast" => Some(Preset::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Dark => "dark",
+            Preset::Light => "light",
+            Preset::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn next(self) -> Preset {
+        match self {
+            Preset::Default => Preset::Dark,
+            Preset::Dark => Preset::Light,
+            Preset::Light => Preset::HighContrast,
+            Preset::HighContrast => Preset::Default,
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        match self {
+            Preset::Default => Theme {
+                accent: Color::Blue,
+                muted: Color::DarkGray,
+                success: Color::Green,
+                error: Color::Red,
+                warning: Color::Yellow,
+                info: Color::Cyan,
+                special: Color::Magenta,
+                on_badge: Color::Black,
+                chip: Color::Gray,
+                chip_background: Color::DarkGray,
+            },
+            Preset::Dark => Theme {
+                accent: Color::LightBlue,
+                muted: Color::Gray,
+                success: Color::LightGreen,
+                error: Color::LightRed,
+                warning: Color::LightYellow,
+                info: Color::LightCyan,
+                special: Color::LightMagenta,
+                on_badge: Color::Black,
+                chip: Color::White,
+                chip_background: Color::DarkGray,
+            },
+            // yellow and cyan are hard to read on white, so 256-color
+            // orange and teal stand in for them
+            Preset::Light => Theme {
+                accent: Color::Blue,
+                muted: Color::Gray,
+                success: Color::Green,
+                error: Color::Red,
+                warning: Color::Indexed(130),
+                info: Color::Indexed(30),
+                special: Color::Magenta,
+                on_badge: Color::White,
+                chip: Color::Black,
+                chip_background: Color::Indexed(252),
+            },
+            Preset::HighContrast => Theme {
+                accent: Color::LightCyan,
+                muted: Color::White,
+                success: Color::LightGreen,
+                error: Color::LightRed,
+                warning: Color::LightYellow,
+                info: Color::LightCyan,
+                special: Color::LightMagenta,
+                on_badge: Color::Black,
+                chip: Color::Black,
+                chip_background: Color::White,
+            },
+        }
+    }
+}
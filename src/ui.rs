@@ -0,0 +1,1187 @@
+//! Rendering of the whole screen.
+
+use ratatui::{
+    prelude::*,
+    widgets::{
+        Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
+};
+
+use std::{
+    borrow::Cow,
+    ptr,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{Danger, PaneLayout},
+    fuzzy, history,
+    input::TextInput,
+    keymap, search_terms,
+    theme::{Preset, Theme},
+    App, AppMode, OutputLine, SortMode, StreamView,
+};
+
+// below this the panes can't show anything useful
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 9;
+// below this the status and debug bars are dropped to make room
+const COMPACT_HEIGHT: u16 = 18;
+
+const SEARCH_HEIGHT: u16 = 3;
+const STATUS_HEIGHT: u16 = 1;
+const DEBUG_HEIGHT: u16 = 3;
+// the progress bar in the status bar, at most
+const GAUGE_WIDTH: u16 = 24;
+// a bordered pane with room for a single row
+const MIN_PANE_HEIGHT: u16 = 3;
+
+struct Areas {
+    search: Option<Rect>,
+    list: Option<Rect>,
+    output: Rect,
+    status: Option<Rect>,
+    debug: Option<Rect>,
+}
+
+// splits the screen, shedding chrome as the terminal gets shorter; `None`
+// means it's too small to draw the app at all
+fn layout(area: Rect, app: &App) -> Option<Areas> {
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        return None;
+    }
+
+    let compact = area.height < COMPACT_HEIGHT;
+    if app.fullscreen_output {
+        // only the output, plus the search box while it's used as a prompt
+        let prompt = matches!(
+            app.mode,
+            AppMode::Searching
+                | AppMode::FilteringOutput
+                | AppMode::Prompting
+                | AppMode::Confirming
+                | AppMode::EditingCommand
+        );
+        let rows = Layout::vertical([
+            Constraint::Length(if prompt { SEARCH_HEIGHT } else { 0 }),
+            Constraint::Fill(1),
+            Constraint::Length(if compact { 0 } else { STATUS_HEIGHT }),
+        ])
+        .split(area);
+        return Some(Areas {
+            search: prompt.then_some(rows[0]),
+            list: None,
+            output: rows[1],
+            status: (!compact).then_some(rows[2]),
+            debug: None,
+        });
+    }
+
+    let chrome = if compact {
+        SEARCH_HEIGHT
+    } else {
+        SEARCH_HEIGHT + STATUS_HEIGHT + DEBUG_HEIGHT
+    };
+    let available = area.height - chrome;
+    // side by side needs room for two panes, otherwise they're stacked
+    let side_by_side = app.pane_layout == PaneLayout::Horizontal && area.width >= 2 * MIN_WIDTH;
+
+    let rows = Layout::vertical([
+        Constraint::Length(SEARCH_HEIGHT),
+        Constraint::Length(available),
+        Constraint::Length(if compact { 0 } else { STATUS_HEIGHT }),
+        Constraint::Length(if compact { 0 } else { DEBUG_HEIGHT }),
+    ])
+    .split(area);
+
+    let [list, output] = if side_by_side {
+        // list gets a third of the width, output the rest
+        let list_width = (area.width / 3).max(MIN_WIDTH);
+        Layout::horizontal([Constraint::Length(list_width), Constraint::Fill(1)]).areas(rows[1])
+    } else {
+        // list gets a third of the height, output the rest, both stay visible
+        let list_height = (available / 3).max(MIN_PANE_HEIGHT);
+        Layout::vertical([Constraint::Length(list_height), Constraint::Fill(1)]).areas(rows[1])
+    };
+
+    Some(Areas {
+        search: Some(rows[0]),
+        list: Some(list),
+        output,
+        status: (!compact).then_some(rows[2]),
+        debug: (!compact).then_some(rows[3]),
+    })
+}
+
+pub fn ui(frame: &mut Frame, app: &mut App) {
+    let Some(areas) = layout(frame.area(), app) else {
+        render_too_small(frame, &app.theme.theme());
+        return;
+    };
+
+    if let Some(area) = areas.search {
+        render_search(frame, app, area);
+    }
+    if let Some(area) = areas.list {
+        if app.mode == AppMode::History {
+            render_history(frame, app, area);
+        } else {
+            render_list(frame, app, area);
+        }
+    }
+    render_output(frame, app, areas.output);
+    if let Some(area) = areas.status {
+        render_status(frame, app, area);
+    }
+    if let Some(area) = areas.debug {
+        render_debug(frame, app, area);
+    }
+    render_mode_badge(frame, app, areas.search.unwrap_or(areas.output));
+    if let (AppMode::Banner, Some(message)) = (app.mode, &app.banner) {
+        render_banner(frame, message, &app.theme.theme());
+    }
+    if app.mode == AppMode::Palette {
+        render_palette(frame, app);
+    }
+    if app.mode == AppMode::Help {
+        render_help(frame, app);
+    }
+}
+
+// the current mode, on the right end of the top border of `area`
+fn render_mode_badge(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme.theme();
+    let color = match app.mode {
+        AppMode::Normal => theme.accent,
+        AppMode::Searching | AppMode::FilteringOutput => theme.warning,
+        AppMode::Prompting | AppMode::EditingCommand => theme.info,
+        AppMode::Confirming | AppMode::Banner => theme.error,
+        AppMode::History | AppMode::Palette | AppMode::Help => theme.special,
+        AppMode::Running => theme.success,
+    };
+    let badge = format!(" {} ", app.mode.label());
+    let width = badge.len() as u16;
+    // keeps the corner and a bit of the title visible
+    if area.width < width + 2 + MIN_WIDTH / 2 {
+        return;
+    }
+    let badge_area = Rect::new(area.right() - width - 1, area.y, width, 1);
+    frame.render_widget(
+        Paragraph::new(badge).style(Style::default().fg(theme.on_badge).bg(color).bold()),
+        badge_area,
+    );
+}
+
+// a `width` x `height` rect in the middle of `area`, shrunk to fit
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [_, row, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(height.min(area.height)),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    let [_, rect, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(width.min(area.width)),
+        Constraint::Fill(1),
+    ])
+    .areas(row);
+    rect
+}
+
+/// A single centered line on an otherwise empty screen, drawn while the app
+/// is still starting up, before the configured theme is known.
+pub fn loading(frame: &mut Frame, text: &str) {
+    let [_, line, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    frame.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .fg(Preset::default().theme().muted),
+        line,
+    );
+}
+
+// a box over the middle of the screen, cleared so nothing shows through
+fn render_banner(frame: &mut Frame, message: &str, theme: &Theme) {
+    let area = frame.area();
+    let width = area.width.saturating_sub(4).min(80);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    // rough wrapped height, the paragraph wraps on words so leave some slack
+    let rows: usize = message
+        .lines()
+        .map(|line| line.chars().count() / inner_width + 1)
+        .sum();
+    let banner = centered(area, width, rows as u16 + 3);
+
+    let block = Block::default()
+        .title("Error ('enter' or 'esc' to dismiss)")
+        .borders(Borders::ALL)
+        .fg(theme.error);
+    frame.render_widget(Clear, banner);
+    frame.render_widget(
+        Paragraph::new(message)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        banner,
+    );
+}
+
+fn render_too_small(frame: &mut Frame, theme: &Theme) {
+    let area = frame.area();
+    let [_, message_area, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+
+    frame.render_widget(
+        Paragraph::new("terminal too small")
+            .alignment(Alignment::Center)
+            .fg(theme.error),
+        message_area,
+    );
+}
+
+fn render_search(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme.theme();
+    // the box doubles as the prompt for the output filter, placeholders and
+    // confirmations; `input` is the text being edited, if any
+    let (title, prefix, input) = match (app.mode, &app.prompt) {
+        (AppMode::Searching, _) if app.alias_hit().is_some() => (
+            format!(
+                "Search (alias of `{}`, 'enter' to run it, 'esc' to clear)",
+                app.alias_hit()
+                    .map_or("", |idx| app.commands[idx].command.as_str())
+            ),
+            "/".to_string(),
+            Some(&app.search_input),
+        ),
+        (AppMode::Searching, _) if app.search_runs_first => (
+            "Search (type to filter, 'enter' to run the top match, 'esc' to clear)".to_string(),
+            "/".to_string(),
+            Some(&app.search_input),
+        ),
+        (AppMode::Searching, _) => (
+            "Search (type to filter, ↑/↓ to pick, 'enter' to keep the filter, 'esc' to clear)"
+                .to_string(),
+            "/".to_string(),
+            Some(&app.search_input),
+        ),
+        (AppMode::FilteringOutput, _) => (
+            "Filter output (type to show matching lines, 'enter' to keep, 'esc' to clear)"
+                .to_string(),
+            "&".to_string(),
+            Some(&app.output_filter),
+        ),
+        (AppMode::Prompting, Some(prompt)) => (
+            format!(
+                "{} ({}/{}, 'enter' to continue, 'esc' to cancel)",
+                prompt.entry.command,
+                prompt.values.len() + 1,
+                prompt.names.len()
+            ),
+            format!("{}: ", prompt.name()),
+            Some(&prompt.input),
+        ),
+        (AppMode::EditingCommand, _) => (
+            "Edit before running ('enter' to run once, 'esc' to cancel, the config is not changed)"
+                .to_string(),
+            "$ ".to_string(),
+            app.inline_edit.as_ref().map(|edit| &edit.input),
+        ),
+        (AppMode::Confirming, _) => {
+            let entry = app
+                .confirming
+                .as_ref()
+                .map(|confirmation| confirmation.entry(&app.commands));
+            let question = format!(
+                "Run `{}`?",
+                entry
+                    .map(|entry| entry.command.as_str())
+                    .unwrap_or_default()
+            );
+            (
+                "Confirm ('y' to run, 'n' or 'esc' to cancel)".to_string(),
+                match entry.and_then(|entry| entry.confirm_message.as_deref()) {
+                    Some(message) => format!("{} {}", message, question),
+                    None => question,
+                },
+                None,
+            )
+        }
+        _ => (
+            "Search (press '/' to search, 'enter' to navigate in the results)".to_string(),
+            "/".to_string(),
+            Some(&app.search_input),
+        ),
+    };
+    let mut search_block = Block::default().title(title).borders(Borders::ALL);
+    // how many commands the query leaves, kept current by `update_filter`
+    let searching = input.is_some_and(|input| ptr::eq(input, &app.search_input));
+    if searching && !app.search_input.is_empty() {
+        let count = match app.filtered_commands.len() {
+            _ if app.is_filtering() => {
+                Span::styled(" filtering… ", Style::default().fg(theme.muted))
+            }
+            0 => Span::styled(" no matches ", Style::default().fg(theme.error).bold()),
+            1 => Span::styled(" 1 match ", Style::default().fg(theme.success)),
+            n => Span::styled(
+                format!(" {} matches ", n),
+                Style::default().fg(theme.success),
+            ),
+        };
+        search_block = search_block.title_bottom(Line::from(count).right_aligned());
+    }
+    let editing = matches!(
+        app.mode,
+        AppMode::Searching
+            | AppMode::FilteringOutput
+            | AppMode::Prompting
+            | AppMode::EditingCommand
+    );
+
+    // an empty box that isn't being typed in gets a hint instead
+    let search_text = match input {
+        Some(input) if input.is_empty() && !editing => Line::from(vec![
+            Span::raw(prefix.clone()),
+            Span::styled(
+                "type to filter…",
+                Style::default().fg(theme.muted).add_modifier(Modifier::DIM),
+            ),
+        ]),
+        _ => Line::raw(format!(
+            "{}{}",
+            prefix,
+            input.map(TextInput::as_str).unwrap_or_default()
+        )),
+    };
+
+    frame.render_widget(Paragraph::new(search_text).block(search_block), area);
+    if let Some(input) = input.filter(|_| editing) {
+        // the border and the prefix come before the text
+        let offset = Span::raw(format!("{}{}", prefix, input.before_cursor())).width() as u16;
+        frame.set_cursor_position((area.x + 1 + offset, area.y + 1));
+    }
+}
+
+// the query on top, the ranked commands below it, over whatever is on screen
+fn render_palette(frame: &mut Frame, app: &App) {
+    let theme = app.theme.theme();
+    let Some(palette) = &app.palette else {
+        return;
+    };
+    let area = frame.area();
+    let height = (palette.results.len() as u16 + 3).clamp(4, 15);
+    let palette_area = centered(area, area.width.saturating_sub(4).min(70), height);
+
+    let block = Block::default()
+        .title("Go to command ('enter' to jump, 'esc' to close)")
+        .borders(Borders::ALL)
+        .fg(theme.accent);
+    let inner = block.inner(palette_area);
+    let [input_area, results_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(Clear, palette_area);
+    frame.render_widget(block, palette_area);
+
+    let prefix = "> ";
+    frame.render_widget(
+        Paragraph::new(format!("{}{}", prefix, palette.input.as_str())),
+        input_area,
+    );
+    let offset = Span::raw(format!("{}{}", prefix, palette.input.before_cursor())).width() as u16;
+    frame.set_cursor_position((input_area.x + offset, input_area.y));
+
+    if palette.results.is_empty() {
+        frame.render_widget(
+            Paragraph::new("no matching commands").fg(theme.muted),
+            results_area,
+        );
+        return;
+    }
+    let query = palette.input.as_str();
+    let items: Vec<ListItem> = palette
+        .results
+        .iter()
+        .map(|&index| {
+            let entry = &app.commands[index];
+            let positions = fuzzy::find(query, &entry.command)
+                .map(|found| found.positions)
+                .unwrap_or_default();
+            let mut spans: Vec<Span> = entry
+                .command
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if positions.contains(&i) {
+                        Span::styled(c.to_string(), Style::default().fg(theme.warning).bold())
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect();
+            if let Some(description) = &entry.description {
+                spans.push(Span::styled(
+                    format!("  {}", description),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().fg(theme.accent))
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(
+        list,
+        results_area,
+        &mut ratatui::widgets::ListState::default().with_selected(Some(palette.selected)),
+    );
+}
+
+// the key bindings matching the query, grouped by mode, over whatever is on
+// screen
+fn render_help(frame: &mut Frame, app: &mut App) {
+    let theme = app.theme.theme();
+    let Some(help) = app.help.as_mut() else {
+        return;
+    };
+    let mut lines = Vec::new();
+    for (group, bindings) in keymap::matching(help.input.as_str(), help.previous) {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::styled(
+            group,
+            Style::default().fg(theme.accent).bold(),
+        ));
+        for binding in bindings {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<24}", binding.label()),
+                    Style::default().fg(theme.warning),
+                ),
+                Span::raw(binding.action),
+            ]));
+        }
+    }
+
+    let area = frame.area();
+    let help_area = centered(
+        area,
+        area.width.saturating_sub(4).min(76),
+        area.height.saturating_sub(2),
+    );
+    let block = Block::default()
+        .title("Keys (type to search, ↑/↓ to scroll, 'esc' to close)")
+        .borders(Borders::ALL)
+        .fg(theme.accent);
+    let inner = block.inner(help_area);
+    let [input_area, _, lines_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(inner);
+    frame.render_widget(Clear, help_area);
+    frame.render_widget(block, help_area);
+
+    let prefix = "? ";
+    frame.render_widget(
+        Paragraph::new(format!("{}{}", prefix, help.input.as_str())).reset(),
+        input_area,
+    );
+    let offset = Span::raw(format!("{}{}", prefix, help.input.before_cursor())).width() as u16;
+    frame.set_cursor_position((input_area.x + offset, input_area.y));
+
+    if lines.is_empty() {
+        frame.render_widget(
+            Paragraph::new("no matching keys").fg(theme.muted),
+            lines_area,
+        );
+        return;
+    }
+    help.scroll = help
+        .scroll
+        .min(lines.len().saturating_sub(lines_area.height as usize));
+    frame.render_widget(
+        Paragraph::new(lines)
+            .reset()
+            .scroll((help.scroll as u16, 0)),
+        lines_area,
+    );
+}
+
+// takes the place of the command list while a past run is picked
+fn render_history(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme.theme();
+    let items: Vec<ListItem> = app
+        .history
+        .iter()
+        .enumerate()
+        .map(|(i, run)| {
+            let glyph = match run.exit_code {
+                Some(0) => Span::styled("✓ ", Style::default().fg(theme.success)),
+                Some(_) => Span::styled("✗ ", Style::default().fg(theme.error)),
+                None => Span::raw("  "),
+            };
+            let mut spans = vec![glyph, Span::raw(run.command.clone())];
+            if let Some(code) = run.exit_code.filter(|&code| code != 0) {
+                spans.push(Span::styled(
+                    format!("  exit {}", code),
+                    Style::default().fg(theme.error),
+                ));
+            }
+            // recomputed every redraw, so `5m ago` keeps up by itself
+            let finished = if app.history_absolute {
+                history::format_absolute(run.finished_at)
+            } else {
+                history::format_ago(run.finished_at)
+            };
+            spans.push(Span::styled(
+                format!("  {}", finished),
+                Style::default().fg(theme.muted),
+            ));
+            if Some(i) == app.viewing_run {
+                spans.push(Span::styled("  (shown)", Style::default().fg(theme.muted)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let title = format!(
+        "History ('enter' to show, 't' for {} times, 'esc' to go back)",
+        if app.history_absolute {
+            "relative"
+        } else {
+            "absolute"
+        }
+    );
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(theme.accent))
+        .highlight_symbol(">> ");
+
+    frame.render_stateful_widget(
+        list,
+        area,
+        &mut ratatui::widgets::ListState::default().with_selected(Some(app.history_selected)),
+    );
+}
+
+fn render_list(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme.theme();
+    let mut title = match app.sort_mode {
+        SortMode::Config => "Commands".to_string(),
+        sort => format!("Commands [sort: {}]", sort.name()),
+    };
+    if app.failed_only {
+        title.push_str(" [failed only, '!' to show all]");
+    }
+    if app.is_filtering() {
+        title.push_str(" [filtering…]");
+    }
+    let list_block = Block::default().title(title).borders(Borders::ALL);
+    if app.filtered_commands.is_empty() {
+        // an empty box gives no hint that the search is what hides everything
+        let [_, hint_area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(list_block.inner(area));
+        let hint = if app.is_filtering() {
+            "Filtering…".to_string()
+        } else if app.failed_only && app.search_input.is_empty() {
+            "No failed commands".to_string()
+        } else if app.search_input.is_empty() {
+            "No commands configured".to_string()
+        } else {
+            format!("No commands match '{}'", app.search_input.as_str())
+        };
+
+        frame.render_widget(list_block, area);
+        frame.render_widget(
+            Paragraph::new(hint)
+                .alignment(Alignment::Center)
+                .fg(theme.muted),
+            hint_area,
+        );
+        return;
+    }
+
+    // `tag:` terms only filter, the rest is highlighted where it matched
+    let (searched_tags, query) = search_terms(app.search_input.as_str());
+    let items: Vec<ListItem> = app
+        .filtered_commands
+        .iter()
+        .enumerate()
+        .filter_map(|(position, &index)| Some((position, index, app.commands.get(index)?)))
+        .map(|(position, index, entry)| {
+            // commands for other operating systems stay listed, but dimmed
+            let compatible = entry.supports_current_os();
+            let display_text = if app.mode == AppMode::Running && Some(index) == app.running_index {
+                let elapsed = app
+                    .started_at
+                    .map(|started| format_elapsed(started.elapsed()))
+                    .unwrap_or_default();
+                match app.steps.as_ref().filter(|steps| steps.is_chain()) {
+                    Some(steps) => format!(
+                        "{} {} {} ({})",
+                        entry.command,
+                        app.activity(),
+                        elapsed,
+                        steps.describe()
+                    ),
+                    None => format!("{} {} {}", entry.command, app.activity(), elapsed),
+                }
+            } else {
+                entry.command.clone()
+            };
+
+            // quick-select badge for the first nine entries
+            let badge = if position < 9 {
+                format!("{} ", position + 1)
+            } else {
+                "  ".to_string()
+            };
+            let mut spans = vec![Span::styled(badge, Style::default().fg(theme.muted))];
+            match app.last_exit(index) {
+                Some(0) => spans.push(Span::styled("✓ ", Style::default().fg(theme.success))),
+                Some(_) => spans.push(Span::styled("✗ ", Style::default().fg(theme.error))),
+                None => {}
+            }
+            if app.is_favorite(index) {
+                spans.push(Span::styled("★ ", Style::default().fg(theme.warning)));
+            }
+            if let Some(icon) = &entry.icon {
+                spans.push(Span::styled(
+                    format!("{} ", icon),
+                    Style::default().fg(theme.warning),
+                ));
+            }
+            let danger = match entry.danger {
+                Danger::Low => Style::default(),
+                Danger::Medium => Style::default().fg(theme.warning),
+                Danger::High => Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD),
+            };
+            spans.extend(highlight_matches(
+                &display_text,
+                &query,
+                danger,
+                theme.warning,
+            ));
+            if !entry.aliases.is_empty() {
+                spans.push(Span::styled(
+                    format!(" ({})", entry.aliases.join(", ")),
+                    Style::default().fg(theme.info),
+                ));
+            }
+            if let Some(description) = &entry.description {
+                spans.push(Span::raw("  "));
+                spans.extend(highlight_matches(
+                    description,
+                    &query,
+                    Style::default().fg(theme.muted),
+                    theme.warning,
+                ));
+            }
+            for tag in &entry.tags {
+                // the tags the search asks for stand out
+                let chip = if searched_tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    Style::default().fg(theme.on_badge).bg(theme.info)
+                } else {
+                    Style::default().fg(theme.chip).bg(theme.chip_background)
+                };
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(format!("#{}", tag), chip));
+            }
+            if !compatible {
+                spans.push(Span::raw(format!("  [{}]", entry.os.join(", "))));
+            }
+            let item = ListItem::new(Line::from(spans));
+            if compatible {
+                item
+            } else {
+                item.style(Style::default().fg(theme.muted).add_modifier(Modifier::DIM))
+            }
+        })
+        .collect();
+
+    // create list widget
+    let list = List::new(items)
+        .block(list_block)
+        .highlight_style(Style::default().fg(theme.accent))
+        .highlight_symbol(">> ");
+
+    frame.render_stateful_widget(
+        list,
+        area,
+        &mut ratatui::widgets::ListState::default().with_selected(
+            app.filtered_commands
+                .iter()
+                .position(|&idx| Some(idx) == app.selected_index),
+        ),
+    );
+}
+
+fn render_output(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme.theme();
+    // sized first, the scroll position depends on the width when wrapping
+    let output_area = Block::default().borders(Borders::ALL).inner(area);
+    let resized = (app.output_width, app.output_height) != (output_area.width, output_area.height);
+    app.output_width = output_area.width;
+    app.output_height = output_area.height;
+    let rows = app.output_rows();
+    if resized {
+        app.clamp_output_scroll(rows);
+    }
+    let height = app.output_height as usize;
+    let offset = app.output_offset();
+
+    let mut output_title = if app.auto_follow {
+        "Output [following]".to_string()
+    } else {
+        "Output [frozen]".to_string()
+    };
+    if let Some(host) = app.steps.as_ref().and_then(|steps| steps.host.as_deref()) {
+        output_title.push_str(&format!(" [remote: {}]", host));
+    }
+    if !app.wrap_output {
+        output_title.push_str(&format!(" [no wrap, col {}]", app.output_hscroll + 1));
+    }
+    if app.stream_view != StreamView::All {
+        output_title.push_str(&format!(
+            " [{}: {} of {} lines, 'E' for {}]",
+            app.stream_view.name(),
+            app.visible_output_lines().count(),
+            app.command_output.lines().count(),
+            app.stream_view.next().name()
+        ));
+    }
+    if app.collapse_repeats {
+        output_title.push_str(" [repeats collapsed, 'd' to show all]");
+    }
+    if app.fullscreen_output {
+        output_title.push_str(" [fullscreen, 'o' to go back]");
+    }
+    if let Some(pattern) = app.active_output_filter() {
+        output_title.push_str(&format!(
+            " [filter \"{}\": {} of {} lines, 'F' to show all]",
+            pattern,
+            app.visible_output_lines().count(),
+            app.command_output.lines().count()
+        ));
+    }
+    if rows > height {
+        output_title.push_str(&format!(
+            " [{}-{} of {}]",
+            offset + 1,
+            (offset + height).min(rows),
+            rows
+        ));
+    }
+    let output_block = Block::default().title(output_title).borders(Borders::ALL);
+
+    if app.mode == AppMode::Running && !app.received_output {
+        // nothing streamed yet, make it obvious the command is still starting
+        let [_, placeholder_area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(output_block.inner(area));
+        let command = app.running_command.as_deref().unwrap_or_default();
+
+        frame.render_widget(output_block, area);
+        frame.render_widget(
+            Paragraph::new(format!("{} Running {}…", app.get_spinner_char(), command))
+                .alignment(Alignment::Center)
+                .fg(theme.muted),
+            placeholder_area,
+        );
+    } else if app.wrap_output && (app.show_timestamps || app.show_line_numbers) {
+        let numbers = app.line_number_width();
+        let lines: Vec<Line> = app
+            .visible_output_lines()
+            .map(|line| {
+                Line::from(vec![
+                    Span::styled(
+                        gutter(&line, numbers, app.show_timestamps),
+                        Style::default().fg(theme.muted),
+                    ),
+                    Span::raw(line.text),
+                ])
+            })
+            .collect();
+        let offset = u16::try_from(offset).unwrap_or(u16::MAX);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(output_block)
+                .wrap(Wrap { trim: true })
+                .scroll((offset, 0)),
+            area,
+        );
+    } else if app.wrap_output {
+        let text = if app.active_output_filter().is_some()
+            || app.collapse_repeats
+            || app.stream_view != StreamView::All
+        {
+            let lines: Vec<Cow<str>> = app.visible_output_lines().map(|line| line.text).collect();
+            Cow::Owned(lines.join("\n"))
+        } else {
+            Cow::Borrowed(app.command_output.as_str())
+        };
+        let offset = u16::try_from(offset).unwrap_or(u16::MAX);
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(output_block)
+                .wrap(Wrap { trim: true })
+                .scroll((offset, 0)),
+            area,
+        );
+    } else {
+        // only the visible window is cut out here, `Paragraph::scroll` takes
+        // u16 offsets which a long minified line or a big log overflows
+        let width = app.output_width as usize;
+        let numbers = app.line_number_width();
+        let lines: Vec<Line> = app
+            .visible_output_lines()
+            .skip(offset)
+            .take(app.output_height as usize)
+            .map(|line| {
+                let gutter = gutter(&line, numbers, app.show_timestamps);
+                if gutter.is_empty() {
+                    return Line::raw(
+                        slice_chars(&line.text, app.output_hscroll, width).to_string(),
+                    );
+                }
+                // the gutter stays put while the line scrolls sideways
+                let rest = width.saturating_sub(gutter.len());
+                Line::from(vec![
+                    Span::styled(gutter, Style::default().fg(theme.muted)),
+                    Span::raw(slice_chars(&line.text, app.output_hscroll, rest).to_string()),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(output_block), area);
+    }
+
+    if rows > height {
+        // drawn over the right border
+        let mut state = ScrollbarState::new(rows - height).position(offset);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None),
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut state,
+        );
+    }
+}
+
+/// How many rows `line` takes in a pane `width` columns wide, as counted by
+/// the same wrapper that draws the Output pane with `Wrap { trim: true }`.
+pub fn wrapped_rows(line: &str, width: usize) -> usize {
+    if width == 0 {
+        return 1;
+    }
+    Paragraph::new(line)
+        .wrap(Wrap { trim: true })
+        .line_count(width.try_into().unwrap_or(u16::MAX))
+}
+
+// the `take` chars of `line` after the first `skip`, cut on char boundaries
+fn slice_chars(line: &str, skip: usize, take: usize) -> &str {
+    let start = line.char_indices().nth(skip).map_or(line.len(), |(i, _)| i);
+    let rest = &line[start..];
+    let end = rest.char_indices().nth(take).map_or(rest.len(), |(i, _)| i);
+    &rest[..end]
+}
+
+fn render_status(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = app.theme.theme();
+    if let Some(message) = &app.status_message {
+        frame.render_widget(Paragraph::new(message.as_str()).fg(theme.warning), area);
+    } else if let (Some(at), Some(steps)) = (app.retry_at, &app.steps) {
+        let left = at.saturating_duration_since(Instant::now());
+        let hint = format!(
+            "retry {}/{} in {:.1}s · 'esc' cancel",
+            steps.attempt,
+            steps.retries,
+            left.as_secs_f64()
+        );
+        frame.render_widget(Paragraph::new(hint).fg(theme.warning), area);
+    } else if let Some(pid) = app.child_pid().filter(|_| app.mode == AppMode::Running) {
+        let mut hint = format!(
+            "pid {} · 'x' terminate · 'X' kill · ctrl+c interrupt · 'esc' or 'del' on it cancel",
+            pid
+        );
+        if let Some(steps) = app.steps.as_ref().filter(|steps| steps.attempt > 0) {
+            hint = format!("retry {}/{} · {}", steps.attempt, steps.retries, hint);
+        }
+        // the progress the output reported, in front of the hint
+        let hint_area = match app.progress {
+            Some(percent) => {
+                let [gauge_area, _, hint_area] = Layout::horizontal([
+                    Constraint::Length(GAUGE_WIDTH.min(area.width / 3)),
+                    Constraint::Length(1),
+                    Constraint::Fill(1),
+                ])
+                .areas(area);
+                frame.render_widget(
+                    Gauge::default()
+                        .gauge_style(Style::default().fg(theme.success).bg(theme.chip_background))
+                        .ratio(percent / 100.0)
+                        .label(format!("{:.0}%", percent)),
+                    gauge_area,
+                );
+                hint_area
+            }
+            None => area,
+        };
+        frame.render_widget(Paragraph::new(hint).fg(theme.muted), hint_area);
+    } else if !app.mouse_capture {
+        frame.render_widget(
+            Paragraph::new("mouse capture off · 'M' to turn it back on").fg(theme.muted),
+            area,
+        );
+    }
+}
+
+fn render_debug(frame: &mut Frame, app: &App, area: Rect) {
+    let debug_block = Block::default().title("debug").borders(Borders::ALL);
+    frame.render_widget(Paragraph::new(app.status_line()).block(debug_block), area);
+}
+
+/// What goes in front of an output line: its number, padded to `numbers`
+/// digits, and its stamp, each only when shown. Padded on the right so a
+/// wrapping paragraph, which trims whitespace in front of a row, keeps the
+/// lines in a column.
+pub fn gutter(line: &OutputLine, numbers: Option<usize>, stamps: bool) -> String {
+    let mut gutter = String::new();
+    if let Some(width) = numbers {
+        gutter.push_str(&format!("{:<width$} ", line.number, width = width));
+    }
+    if stamps {
+        gutter.push_str(&format!(
+            "{:<8} ",
+            format!("+{:.2}s", line.stamp.as_secs_f64())
+        ));
+    }
+    gutter
+}
+
+// `MM:SS`, or `H:MM:SS` once a run passes the hour
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// splits `text` into spans with every case-insensitive occurrence of `query`
+// emphasized, so it's visible which field made a command match
+fn highlight_matches(
+    text: &str,
+    query: &str,
+    style: Style,
+    highlight: Color,
+) -> Vec<Span<'static>> {
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return vec![Span::styled(text.to_string(), style)];
+    }
+
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut rest = text;
+    while let Some((offset, len)) = find_ignore_case(rest, &needle) {
+        let start = text.len() - rest.len() + offset;
+        if start > plain_start {
+            spans.push(Span::styled(text[plain_start..start].to_string(), style));
+        }
+        spans.push(Span::styled(
+            text[start..start + len].to_string(),
+            style.fg(highlight).add_modifier(Modifier::BOLD),
+        ));
+        plain_start = start + len;
+        rest = &text[plain_start..];
+    }
+    if plain_start < text.len() {
+        spans.push(Span::styled(text[plain_start..].to_string(), style));
+    }
+    spans
+}
+
+// byte offset and length of the first match of the already lowercased `needle`
+fn find_ignore_case(haystack: &str, needle: &[char]) -> Option<(usize, usize)> {
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut expected = needle.iter();
+        let mut end = start;
+        for c in haystack[start..].chars() {
+            for lower in c.to_lowercase() {
+                if expected.next() != Some(&lower) {
+                    return None;
+                }
+            }
+            end += c.len_utf8();
+            if expected.len() == 0 {
+                return Some((start, end - start));
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_app;
+    use ratatui::backend::TestBackend;
+
+    fn screen(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| ui(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn slicing_cuts_on_char_boundaries() {
+        let line = "ü→€😀".repeat(500);
+        assert_eq!(slice_chars(&line, 0, 3), "ü→€");
+        assert_eq!(slice_chars(&line, 3, 2), "😀ü");
+        assert_eq!(slice_chars(&line, 1999, 10), "😀");
+        assert_eq!(slice_chars(&line, 2000, 10), "");
+        assert_eq!(slice_chars(&line, 5000, 10), "");
+        assert_eq!(slice_chars(&line, 997, 2000).chars().count(), 1003);
+    }
+
+    // the rows a wrapped paragraph of `line` fills when it's drawn
+    fn drawn_rows(line: &str, width: u16) -> usize {
+        let mut terminal = Terminal::new(TestBackend::new(width, 200)).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_widget(Paragraph::new(line).wrap(Wrap { trim: true }), frame.area())
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = buffer
+            .content()
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect();
+        rows.iter()
+            .rposition(|row| !row.trim().is_empty())
+            .map_or(1, |last| last + 1)
+    }
+
+    #[test]
+    fn wrapped_rows_match_the_drawn_paragraph() {
+        for line in [
+            "",
+            "short",
+            "  leading spaces and   repeated    gaps here",
+            "averyveryverylongwordthatbreaks and then  some",
+            "a b c d e f g h i j k l m n o p",
+            "wide 字符 也会 换行 的 文本",
+            "     ",
+        ] {
+            for width in [2, 3, 5, 8, 13] {
+                assert_eq!(
+                    wrapped_rows(line, width as usize),
+                    drawn_rows(line, width),
+                    "{:?} at width {}",
+                    line,
+                    width
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn too_short_for_the_panes() {
+        let mut app = test_app(&["ls"]);
+        assert!(layout(Rect::new(0, 0, 80, 5), &app).is_none());
+        assert!(screen(&mut app, 80, 5).contains("terminal too small"));
+    }
+
+    #[test]
+    fn short_terminals_drop_the_bars() {
+        let app = test_app(&["ls"]);
+        let areas = layout(Rect::new(0, 0, 80, 10), &app).unwrap();
+        assert!(areas.status.is_none());
+        assert!(areas.debug.is_none());
+        assert_eq!(areas.search.unwrap().height, SEARCH_HEIGHT);
+        let list = areas.list.unwrap();
+        assert!(list.height >= MIN_PANE_HEIGHT);
+        assert!(areas.output.height >= MIN_PANE_HEIGHT);
+        assert_eq!(SEARCH_HEIGHT + list.height + areas.output.height, 10);
+    }
+
+    #[test]
+    fn tall_terminals_show_everything() {
+        let mut app = test_app(&["ls"]);
+        let areas = layout(Rect::new(0, 0, 80, 20), &app).unwrap();
+        assert_eq!(areas.status.unwrap().height, STATUS_HEIGHT);
+        assert_eq!(areas.debug.unwrap().height, DEBUG_HEIGHT);
+        assert!(areas.output.height >= MIN_PANE_HEIGHT);
+        let screen = screen(&mut app, 80, 20);
+        assert!(screen.contains("debug"));
+        assert!(!screen.contains("terminal too small"));
+    }
+
+    #[test]
+    fn fullscreen_output_keeps_the_prompts() {
+        let mut app = test_app(&["ls"]);
+        app.fullscreen_output = true;
+        for mode in [
+            AppMode::Searching,
+            AppMode::FilteringOutput,
+            AppMode::Prompting,
+            AppMode::Confirming,
+            AppMode::EditingCommand,
+        ] {
+            app.mode = mode;
+            let areas = layout(Rect::new(0, 0, 80, 20), &app).unwrap();
+            assert_eq!(areas.search.map(|area| area.height), Some(SEARCH_HEIGHT));
+        }
+    }
+
+    #[test]
+    fn fullscreen_output_fills_the_screen() {
+        let mut app = test_app(&["ls"]);
+        app.fullscreen_output = true;
+        for height in [10, 20] {
+            let areas = layout(Rect::new(0, 0, 80, height), &app).unwrap();
+            assert!(areas.list.is_none());
+            assert!(areas.search.is_none());
+            let status = if height < COMPACT_HEIGHT {
+                0
+            } else {
+                STATUS_HEIGHT
+            };
+            assert_eq!(areas.output.height, height - status);
+        }
+    }
+}